@@ -0,0 +1,83 @@
+use std::collections::HashMap;
+
+use argon2::{
+    password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::app::AppError;
+
+const MIN_PASSWORD: usize = 8;
+
+// a registered user, the password is only kept as a salted hash
+#[derive(Clone, Deserialize, Serialize)]
+pub struct Account {
+    pub username: String,
+    hash: String, // phc string with the salt and parameters
+}
+impl Account {
+    pub fn verify(&self, password: &str) -> bool {
+        PasswordHash::new(&self.hash).is_ok_and(|hash| {
+            Argon2::default()
+                .verify_password(password.as_bytes(), &hash)
+                .is_ok()
+        })
+    }
+}
+
+// slow on purpose, so run it off the async runtime
+pub fn hash(password: &str) -> Result<String, AppError> {
+    if password.chars().count() < MIN_PASSWORD {
+        return Err(AppError(format!(
+            "password must be at least {} characters",
+            MIN_PASSWORD
+        )));
+    }
+    let salt =
+        SaltString::encode_b64(&rand::random::<[u8; 16]>()).map_err(|e| AppError(e.to_string()))?;
+    let hash = Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map_err(|e| AppError(e.to_string()))?;
+    Ok(hash.to_string())
+}
+
+// registered users, with usernames unique regardless of case
+#[derive(Default)]
+pub struct Accounts {
+    accounts: HashMap<Uuid, Account>,
+    names: HashMap<String, Uuid>,
+}
+impl Accounts {
+    pub fn load(accounts: HashMap<Uuid, Account>) -> Self {
+        let names = accounts
+            .iter()
+            .map(|(&id, account)| (account.username.to_lowercase(), id))
+            .collect();
+        Self { accounts, names }
+    }
+
+    pub fn save(&self) -> HashMap<Uuid, Account> {
+        self.accounts.clone()
+    }
+
+    pub fn get(&self, id: &Uuid) -> Option<&Account> {
+        self.accounts.get(id)
+    }
+
+    pub fn find(&self, username: &str) -> Option<(Uuid, &Account)> {
+        let id = self.names.get(&username.to_lowercase())?;
+        Some((*id, &self.accounts[id]))
+    }
+
+    pub fn insert(&mut self, username: String, hash: String) -> Result<Uuid, AppError> {
+        if self.find(&username).is_some() {
+            return Err("username is taken".into());
+        }
+        let id = Uuid::new_v4();
+        self.names.insert(username.to_lowercase(), id);
+        self.accounts.insert(id, Account { username, hash });
+        Ok(id)
+    }
+}
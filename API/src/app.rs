@@ -1,4 +1,4 @@
-use std::{error::Error, fmt::Display, sync::Arc, time::Duration};
+use std::{collections::HashMap, error::Error, fmt::Display, sync::Arc, time::Duration};
 
 use axum::{
     extract::{
@@ -16,14 +16,23 @@ use rand::{distr::Alphabetic, Rng};
 use serde::{ser::SerializeStruct, Serialize};
 use serde_json::json;
 use tokio::sync::RwLock;
+use tracing::warn;
 use uuid::Uuid;
 
-use crate::lobby::Lobby;
+use crate::account::Accounts;
+use crate::bot;
+use crate::lobby::{Listing, Lobby};
+use crate::matchmaking::Matchmaking;
+use crate::stats::StatsRef;
+use crate::store::Saved;
 pub type LobbyRef = Arc<RwLock<Lobby>>;
 
 #[derive(Clone)]
 pub struct AppState {
-    pub users: Arc<Cache<Uuid, String>>,
+    pub users: Arc<Cache<Uuid, User>>, // by session
+    pub accounts: Arc<RwLock<Accounts>>,
+    pub stats: StatsRef,
+    pub matchmaking: Arc<Matchmaking>,
     lobbies: Arc<Cache<String, LobbyRef>>,
 }
 impl AppState {
@@ -32,6 +41,9 @@ impl AppState {
     pub fn new() -> Self {
         Self {
             users: Arc::new(Cache::builder().time_to_idle(Self::EXPIRATION).build()),
+            accounts: Default::default(),
+            stats: Default::default(),
+            matchmaking: Arc::new(Matchmaking::new()),
             lobbies: Arc::new(Cache::builder().time_to_idle(Self::EXPIRATION).build()),
         }
     }
@@ -50,12 +62,83 @@ impl AppState {
         }
     }
 
-    pub async fn create_lobby(&self) -> (String, LobbyRef) {
+    async fn insert_lobby(&self, id: String, mut lobby: Lobby) -> LobbyRef {
+        lobby.set_stats(self.stats.clone());
+        let rx = lobby.subscribe();
+        let lobby = Arc::new(RwLock::new(lobby));
+        tokio::spawn(bot::drive(Arc::downgrade(&lobby), rx));
+        self.lobbies.insert(id, Arc::clone(&lobby)).await;
+        lobby
+    }
+
+    pub async fn create_lobby(&self, lobby: Lobby) -> (String, LobbyRef) {
         let id = self.generate_lobby_id().await;
-        let lobby = Arc::new(RwLock::new(Lobby::new()));
-        self.lobbies.insert(id.clone(), Arc::clone(&lobby)).await;
+        let lobby = self.insert_lobby(id.clone(), lobby).await;
         (id, lobby)
     }
+
+    // public lobbies ordered by code
+    pub async fn listings(&self) -> Vec<Listing> {
+        let mut lobbies: Vec<_> = self.lobbies.iter().collect();
+        lobbies.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut listings = Vec::new();
+        for (id, lobby) in lobbies {
+            listings.extend(lobby.read().await.listing(id.to_string()));
+        }
+        listings
+    }
+
+    pub async fn snapshot(&self) -> Saved {
+        let mut saved = Saved::default();
+        for (session, user) in self.users.iter() {
+            if user.guest {
+                saved.users.insert(*session, user.username);
+            } else {
+                saved.sessions.insert(*session, user.id);
+            }
+        }
+        saved.accounts = self.accounts.read().await.save();
+        saved.stats = self.stats.lock().unwrap().clone();
+        let lobbies: Vec<_> = self.lobbies.iter().collect();
+        for (id, lobby) in lobbies {
+            saved
+                .lobbies
+                .insert(id.to_string(), lobby.read().await.snapshot());
+        }
+        saved
+    }
+
+    pub async fn restore(&self, saved: Saved) {
+        let accounts = Accounts::load(saved.accounts);
+        for (session, username) in saved.users {
+            let user = User {
+                id: session,
+                username,
+                guest: true,
+            };
+            self.users.insert(session, user).await;
+        }
+        for (session, id) in saved.sessions {
+            if let Some(account) = accounts.get(&id) {
+                let user = User {
+                    id,
+                    username: account.username.clone(),
+                    guest: false,
+                };
+                self.users.insert(session, user).await;
+            }
+        }
+        *self.accounts.write().await = accounts;
+        *self.stats.lock().unwrap() = saved.stats;
+        for (id, snapshot) in saved.lobbies {
+            match Lobby::restore(snapshot) {
+                Ok(lobby) => {
+                    self.insert_lobby(id, lobby).await;
+                }
+                Err(e) => warn!(id, error = e, "could not restore lobby"),
+            }
+        }
+    }
 }
 
 // wraps error string
@@ -88,9 +171,12 @@ impl Display for AppError {
 }
 impl Error for AppError {}
 
+// guests are identified by their session, everyone else by their account
+#[derive(Clone)]
 pub struct User {
     pub id: Uuid,
     pub username: String,
+    pub guest: bool,
 }
 impl FromRequestParts<AppState> for User {
     type Rejection = Response;
@@ -100,7 +186,7 @@ impl FromRequestParts<AppState> for User {
         state: &AppState,
     ) -> Result<Self, Self::Rejection> {
         let jar = CookieJar::from_headers(&parts.headers);
-        let id = jar
+        let session = jar
             .get("session")
             .and_then(|c| Uuid::parse_str(c.value()).ok())
             .ok_or(
@@ -111,7 +197,7 @@ impl FromRequestParts<AppState> for User {
                     .into_response(),
             )?;
 
-        let username = state.users.get(&id).await.ok_or(
+        let user = state.users.get(&session).await.ok_or(
             (
                 StatusCode::FORBIDDEN,
                 jar.remove(Cookie::from("session")),
@@ -120,7 +206,7 @@ impl FromRequestParts<AppState> for User {
                 .into_response(),
         )?;
 
-        Ok(Self { id, username })
+        Ok(user)
     }
 }
 
@@ -131,17 +217,19 @@ impl FromRequestParts<AppState> for LobbyRef {
         parts: &mut Parts,
         state: &AppState,
     ) -> Result<Self, Self::Rejection> {
-        let Path(lobby_code) = Path::<String>::from_request_parts(parts, &state)
+        let missing = || {
+            (
+                StatusCode::BAD_REQUEST,
+                AppError::from("missing lobby code in path"),
+            )
+                .into_response()
+        };
+        let Path(params) = Path::<HashMap<String, String>>::from_request_parts(parts, state)
             .await
-            .map_err(|_| {
-                (
-                    StatusCode::BAD_REQUEST,
-                    AppError::from("missing lobby code in path"),
-                )
-                    .into_response()
-            })?;
+            .map_err(|_| missing())?;
+        let lobby_code = params.get("lobby_code").ok_or_else(missing)?;
 
-        let lobby = state.lobbies.get(&lobby_code).await.ok_or(
+        let lobby = state.lobbies.get(lobby_code).await.ok_or(
             (
                 StatusCode::NOT_FOUND,
                 AppError(format!("lobby {} not found", lobby_code)),
@@ -153,22 +241,6 @@ impl FromRequestParts<AppState> for LobbyRef {
     }
 }
 
-pub struct LobbyIdx(pub LobbyRef, pub Option<usize>);
-impl FromRequestParts<AppState> for LobbyIdx {
-    type Rejection = Response;
-
-    async fn from_request_parts(
-        parts: &mut Parts,
-        state: &AppState,
-    ) -> Result<Self, Self::Rejection> {
-        let lobby = LobbyRef::from_request_parts(parts, &state).await?;
-        let user = User::from_request_parts(parts, &state).await?;
-        let idx = lobby.read().await.user_idx(&user);
-
-        Ok(Self(lobby, idx))
-    }
-}
-
 pub trait SendApp {
     async fn send_json(&mut self, msg: impl Serialize) -> Result<(), Box<dyn Error>>;
     async fn send_result(&mut self, e: Result<(), impl Error>) -> Result<(), Box<dyn Error>>;
@@ -0,0 +1,235 @@
+use std::{env, process, thread};
+
+use rand::{rngs::StdRng, SeedableRng};
+
+use doudizhu::{
+    card,
+    game::Game,
+    rules::{BidMode, Rules},
+    strategy::{Action, Level, Strategy},
+};
+
+const USAGE: &str =
+    "usage: simulate [--games N] [--players 3|4] [--threads N] [--seed N] [--rob] [--double] STRATEGY...
+strategies are easy, medium or hard, given per seat or once for every seat
+--rob bids by robbing the landlord instead of bidding points
+--double lets peasants double and the landlord redouble after bidding
+game i is dealt and played from seed + i, so runs with the same seed are identical";
+
+struct Config {
+    games: usize,
+    players: usize,
+    threads: usize,
+    seed: u64,
+    rules: Rules,
+    levels: Vec<Level>,
+}
+impl Config {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut config = Config {
+            games: 1000,
+            players: 3,
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+            seed: Game::random_seed(),
+            rules: Rules::default(),
+            levels: Vec::new(),
+        };
+
+        let mut rob = false;
+        let mut doubling = false;
+        while let Some(arg) = args.next() {
+            let mut number = || {
+                args.next()
+                    .and_then(|n| n.parse::<u64>().ok())
+                    .ok_or(format!("{} expects a number", arg))
+            };
+            match arg.as_str() {
+                "--games" => config.games = number()? as usize,
+                "--players" => config.players = number()? as usize,
+                "--threads" => config.threads = number()? as usize,
+                "--seed" => config.seed = number()?,
+                "--rob" => rob = true,
+                "--double" => doubling = true,
+                "--help" => return Err(USAGE.to_string()),
+                _ => config.levels.push(arg.parse()?),
+            }
+        }
+
+        config.threads = config.threads.max(1);
+        card::check_players(config.players)?;
+        config.rules = Rules {
+            bid_mode: if rob { BidMode::Rob } else { BidMode::Points },
+            doubling,
+            ..Rules::standard(config.players)
+        };
+        match config.levels.len() {
+            0 => config.levels = vec![Level::Medium; config.players],
+            1 => config.levels = vec![config.levels[0]; config.players],
+            n if n != config.players => {
+                return Err(format!("expected 1 or {} strategies", config.players))
+            }
+            _ => {}
+        }
+        Ok(config)
+    }
+}
+
+// totals for a single seat
+#[derive(Clone, Default)]
+struct Seat {
+    landlord_games: usize,
+    landlord_wins: usize,
+    peasant_games: usize,
+    peasant_wins: usize,
+    score: i64,
+}
+
+#[derive(Clone, Default)]
+struct Stats {
+    games: usize,
+    landlord_wins: usize,
+    landlord_score: i64,
+    bombs: usize,
+    dominations: usize,
+    seats: Vec<Seat>,
+}
+impl Stats {
+    fn merge(&mut self, other: Stats) {
+        self.games += other.games;
+        self.landlord_wins += other.landlord_wins;
+        self.landlord_score += other.landlord_score;
+        self.bombs += other.bombs;
+        self.dominations += other.dominations;
+        self.seats.resize(other.seats.len(), Seat::default());
+        for (seat, other) in self.seats.iter_mut().zip(other.seats) {
+            seat.landlord_games += other.landlord_games;
+            seat.landlord_wins += other.landlord_wins;
+            seat.peasant_games += other.peasant_games;
+            seat.peasant_wins += other.peasant_wins;
+            seat.score += other.score;
+        }
+    }
+}
+
+// play a game through to the end, redealing if no one bids
+fn play_game(config: &Config, strategies: &[&dyn Strategy], seed: u64, stats: &mut Stats) {
+    let players = strategies.len();
+    let mut rng = StdRng::seed_from_u64(seed);
+    let new_game = |seed| {
+        Game::new(players, seed)
+            .expect("players are checked with the arguments")
+            .with_rules(config.rules.clone())
+    };
+    let mut game = new_game(seed);
+    while game.winner().is_none() {
+        let idx = game.turn();
+        match strategies[idx].act(&game.view(idx), &mut rng) {
+            Action::Bid(val) => {
+                if game.bid(idx, val).expect("strategies bid legally") {
+                    game = new_game(game.redeal_seed());
+                }
+            }
+            Action::Double(val) => game.double(idx, val).expect("strategies double legally"),
+            Action::Play(hand) => {
+                if hand.is_bomb() {
+                    stats.bombs += 1;
+                }
+                game.play(idx, hand).expect("strategies play legally");
+            }
+        }
+    }
+
+    let landlord = game.landlord();
+    let landlord_won = game.winner() == Some(landlord);
+    let deltas = game.score_deltas();
+    stats.games += 1;
+    stats.landlord_wins += landlord_won as usize;
+    stats.landlord_score += deltas[landlord] as i64;
+    stats.dominations += game.domination() as usize;
+    for (idx, seat) in stats.seats.iter_mut().enumerate() {
+        if idx == landlord {
+            seat.landlord_games += 1;
+            seat.landlord_wins += landlord_won as usize;
+        } else {
+            seat.peasant_games += 1;
+            seat.peasant_wins += !landlord_won as usize;
+        }
+        seat.score += deltas[idx] as i64;
+    }
+}
+
+fn percent(num: usize, den: usize) -> String {
+    if den == 0 {
+        "-".to_string()
+    } else {
+        format!("{:.1}%", 100.0 * num as f64 / den as f64)
+    }
+}
+
+fn main() {
+    let config = Config::parse(env::args().skip(1)).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(2);
+    });
+    let strategies: Vec<_> = config.levels.iter().map(|l| l.strategy()).collect();
+
+    // deal the games out across threads
+    let mut stats = Stats::default();
+    thread::scope(|s| {
+        let handles: Vec<_> = (0..config.threads)
+            .map(|t| {
+                let config = &config;
+                let strategies = &strategies;
+                s.spawn(move || {
+                    let mut stats = Stats {
+                        seats: vec![Seat::default(); strategies.len()],
+                        ..Default::default()
+                    };
+                    for i in (t..config.games).step_by(config.threads) {
+                        let seed = config.seed.wrapping_add(i as u64);
+                        play_game(config, strategies, seed, &mut stats);
+                    }
+                    stats
+                })
+            })
+            .collect();
+        for handle in handles {
+            stats.merge(handle.join().expect("simulation thread panicked"));
+        }
+    });
+
+    let games = stats.games;
+    println!(
+        "{} games, {} players, {:?} bidding, seed {}",
+        games, config.players, config.rules.bid_mode, config.seed
+    );
+    println!(
+        "landlord wins {}, peasants win {}",
+        percent(stats.landlord_wins, games),
+        percent(games - stats.landlord_wins, games)
+    );
+    println!(
+        "average landlord score delta {:+.2}",
+        stats.landlord_score as f64 / games.max(1) as f64
+    );
+    println!(
+        "bombs per game {:.2}",
+        stats.bombs as f64 / games.max(1) as f64
+    );
+    println!("domination rate {}", percent(stats.dominations, games));
+
+    println!();
+    println!("seat  strategy  landlord wins     peasant wins      avg score");
+    for (idx, (seat, level)) in stats.seats.iter().zip(&config.levels).enumerate() {
+        println!(
+            "{:<5} {:<9} {:>6} of {:<6}  {:>6} of {:<6}  {:+.2}",
+            idx,
+            format!("{:?}", level),
+            percent(seat.landlord_wins, seat.landlord_games),
+            seat.landlord_games,
+            percent(seat.peasant_wins, seat.peasant_games),
+            seat.peasant_games,
+            seat.score as f64 / games.max(1) as f64
+        );
+    }
+}
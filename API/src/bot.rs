@@ -0,0 +1,60 @@
+use std::{sync::Weak, time::Duration};
+
+use tokio::{
+    sync::{
+        broadcast::{self, error::RecvError},
+        RwLock,
+    },
+    task,
+    time::{sleep, sleep_until, Instant},
+};
+
+use crate::lobby::{Lobby, ServerMsg, Update};
+
+// pause before a bot moves so humans can follow along
+const DELAY: Duration = Duration::from_millis(1000);
+
+// moves for bots whenever the lobby state changes
+// and for human players who run out of time
+// exits once the lobby has been dropped
+pub async fn drive(lobby: Weak<RwLock<Lobby>>, mut rx: broadcast::Receiver<Update>) {
+    let mut deadline = None;
+    loop {
+        tokio::select! {
+            update = rx.recv() => match update {
+                Ok(Update {
+                    msg: ServerMsg::State(_),
+                    ..
+                })
+                | Err(RecvError::Lagged(_)) => {}
+                Ok(_) => continue,
+                Err(RecvError::Closed) => break,
+            },
+            _ = sleep_until(deadline.unwrap_or_else(Instant::now)), if deadline.is_some() => {
+                let Some(lobby) = lobby.upgrade() else {
+                    break;
+                };
+                lobby.write().await.expire();
+            }
+        }
+
+        let Some(lobby) = lobby.upgrade() else {
+            break;
+        };
+        let (bot, time_left) = {
+            let lobby = lobby.read().await;
+            (lobby.bot_view(), lobby.time_left())
+        };
+        deadline = time_left.map(|left| Instant::now() + left);
+        let Some((level, view)) = bot else {
+            continue;
+        };
+        sleep(DELAY).await;
+
+        // searching can be slow, so decide without holding the lock
+        let idx = view.idx;
+        let action =
+            task::spawn_blocking(move || level.strategy().act(&view, &mut rand::rng())).await;
+        lobby.write().await.bot_act(idx, action.ok());
+    }
+}
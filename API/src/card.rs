@@ -1,8 +1,10 @@
 use std::fmt::{Display, Formatter};
 
-use rand::seq::SliceRandom;
+use rand::{seq::SliceRandom, Rng};
 use serde::{Deserialize, Serialize};
 
+use crate::rules::Rules;
+
 const SUIT_MAP: [&str; 4] = ["♣️", "♦️", "♥️", "♠️"];
 const RANK_MAP: [&str; 15] = [
     "3", "4", "5", "6", "7", "8", "9", "10", "J", "Q", "K", "A", "2", "J", "J",
@@ -10,7 +12,10 @@ const RANK_MAP: [&str; 15] = [
 #[derive(Eq, PartialEq, PartialOrd, Ord, Copy, Clone, Debug, Deserialize, Serialize)]
 pub struct Card(usize);
 impl Card {
-    fn rank(&self) -> usize {
+    pub fn value(&self) -> usize {
+        self.0
+    }
+    pub fn rank(&self) -> usize {
         match self.0 {
             53 => 14,
             _ => self.0 / 4,
@@ -27,7 +32,7 @@ impl Display for Card {
         write!(f, "{}{}", RANK_MAP[self.rank()], suit)
     }
 }
-pub fn join(cards: &Vec<Card>) -> String {
+pub fn join(cards: &[Card]) -> String {
     cards
         .iter()
         .map(ToString::to_string)
@@ -35,7 +40,24 @@ pub fn join(cards: &Vec<Card>) -> String {
         .join(", ")
 }
 
-#[derive(Eq, PartialEq, Debug)]
+// games are for three players with one deck or four with two
+pub fn check_players(players: usize) -> Result<(), String> {
+    if !(3..=4).contains(&players) {
+        return Err(format!("games are for 3 or 4 players, not {}", players));
+    }
+    Ok(())
+}
+
+// a single deck for three players and a double deck for four
+pub fn deck(players: usize) -> Vec<Card> {
+    if players == 3 {
+        (0..54).map(Card).collect()
+    } else {
+        (0..54).chain(0..54).map(Card).collect()
+    }
+}
+
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
 enum HandName {
     Pass,
     Single,
@@ -66,7 +88,7 @@ impl Display for HandName {
     }
 }
 
-#[derive(Eq, PartialEq, Debug)]
+#[derive(Eq, PartialEq, Clone, Debug)]
 struct HandType {
     name: HandName,
     mult: usize, // length of chain or number of cards in bomb
@@ -96,7 +118,7 @@ impl Serialize for HandType {
     }
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Clone, Debug, Serialize)]
 pub struct Hand {
     kind: HandType,
     #[serde(skip)]
@@ -119,8 +141,8 @@ impl Hand {
         &self.cards
     }
 
-    pub fn new(players: usize, cards: Vec<usize>) -> Result<Self, String> {
-        if cards.len() == 0 {
+    pub fn new(rules: &Rules, cards: Vec<usize>) -> Result<Self, String> {
+        if cards.is_empty() {
             return Ok(Self::PASS);
         }
 
@@ -156,6 +178,8 @@ impl Hand {
         let pair = cnts[2].len();
         let trip = cnts[3].len();
         let bomb: usize = cnts[4..].iter().map(|v| v.len()).sum();
+        // kickers are the singles and pairs attached to triples and quads
+        let kickers_ok = rules.high_kickers || cnts[1].iter().chain(&cnts[2]).all(|&r| r < 12);
 
         let mut hand_type = HandType::PASS;
         if bomb > 0 {
@@ -163,22 +187,22 @@ impl Hand {
                 if single == 0 && pair == 0 {
                     hand_type.name = HandName::Bomb;
                     hand_type.mult = cards.len();
-                } else if players == 3 {
-                    if single == 2 && pair == 0 {
+                } else if kickers_ok {
+                    if single == 2 && pair == 0 && rules.quad_single {
                         hand_type.name = HandName::QuadSingle;
-                    } else if single == 0 && pair == 2 {
+                    } else if single == 0 && pair == 2 && rules.quad_pair {
                         hand_type.name = HandName::QuadPair;
                     }
                 }
             }
         } else if trip > 0 && cnts[3][0] < 13 {
-            if is_valid_chain(&cnts[3], 2) {
+            if is_valid_chain(&cnts[3], rules.min_chain[2]) {
                 hand_type.mult = trip;
                 if single == 0 && pair == 0 {
                     hand_type.name = HandName::Triple;
-                } else if trip == single && pair == 0 && players == 3 {
+                } else if trip == single && pair == 0 && rules.triple_single && kickers_ok {
                     hand_type.name = HandName::TripleSingle;
-                } else if trip == pair && single == 0 {
+                } else if trip == pair && single == 0 && rules.triple_pair && kickers_ok {
                     hand_type.name = HandName::TriplePair;
                 }
             }
@@ -186,15 +210,15 @@ impl Hand {
             if single == 0 {
                 if pair == 2 && cnts[2][0] == 13 {
                     hand_type.name = HandName::Rocket;
-                } else if is_valid_chain(&cnts[2], 3) {
+                } else if is_valid_chain(&cnts[2], rules.min_chain[1]) {
                     hand_type.mult = pair;
                     hand_type.name = HandName::Pair
                 }
             }
         } else {
-            if single == 2 && cnts[1][0] == 13 && players == 3 {
+            if single == 2 && cnts[1][0] == 13 && rules.single_rocket {
                 hand_type.name = HandName::Rocket;
-            } else if is_valid_chain(&cnts[1], 5) {
+            } else if is_valid_chain(&cnts[1], rules.min_chain[0]) {
                 hand_type.mult = single;
                 hand_type.name = HandName::Single;
             }
@@ -215,15 +239,13 @@ impl Hand {
         })
     }
 
-    pub fn deal_hands(players: usize) -> Vec<Vec<Card>> {
-        let mut deck: Vec<usize> = if players == 3 {
-            (0..54).collect()
-        } else {
-            (0..54).chain(0..54).collect()
-        };
-        deck.shuffle(&mut rand::rng());
+    // only 3 or 4 players have a deck
+    pub fn deal_hands(players: usize, rng: &mut impl Rng) -> Result<Vec<Vec<Card>>, String> {
+        check_players(players)?;
+        let mut deck = deck(players);
+        deck.shuffle(rng);
 
-        if players == 3 {
+        let hands = if players == 3 {
             [
                 deck.drain(..17).collect(),
                 deck.drain(..17).collect(),
@@ -244,25 +266,48 @@ impl Hand {
         .into_iter()
         .map(|mut cards| {
             cards.sort();
-            cards.into_iter().map(Card).collect()
+            cards
         })
-        .collect()
+        .collect();
+        Ok(hands)
     }
 
     pub fn is_pass(&self) -> bool {
         self.kind.name == HandName::Pass
     }
 
-    pub fn is_double(&self, players: usize) -> bool {
+    pub fn is_bomb(&self) -> bool {
+        self.kind.name == HandName::Bomb || self.kind.name == HandName::Rocket
+    }
+
+    pub fn is_rocket(&self) -> bool {
         self.kind.name == HandName::Rocket
-            || (self.kind.name == HandName::Bomb && (players == 3 || self.kind.mult >= 6))
     }
 
-    pub fn can_play(&self, last_play: &Self) -> Result<(), String> {
+    // same type and highest rank, ignoring kickers
+    pub fn same_shape(&self, other: &Self) -> bool {
+        self.kind == other.kind && self.sort_key.first() == other.sort_key.first()
+    }
+
+    fn is_quad_kicker(&self) -> bool {
+        self.kind.name == HandName::QuadSingle || self.kind.name == HandName::QuadPair
+    }
+
+    pub fn is_double(&self, rules: &Rules) -> bool {
+        self.kind.name == HandName::Rocket
+            || (self.kind.name == HandName::Bomb && self.kind.mult >= rules.double_bomb_size)
+            || (rules.quad_kicker_bomb && self.is_quad_kicker() && rules.double_bomb_size <= 4)
+    }
+
+    pub fn can_play(&self, last_play: &Self, rules: &Rules) -> Result<(), String> {
         if self.kind.name != last_play.kind.name {
             if last_play.is_pass()
                 || self.kind.name == HandName::Rocket
                 || (self.kind.name == HandName::Bomb && last_play.kind.name != HandName::Rocket)
+                || (rules.quad_kicker_bomb
+                    && self.is_quad_kicker()
+                    && !last_play.is_bomb()
+                    && !last_play.is_quad_kicker())
             {
                 Ok(())
             } else {
@@ -286,146 +331,407 @@ impl Hand {
             }
         }
     }
+
+    // enumerate every hand in cards that can be played on last_play
+    // suits do not matter, so only one set of cards is produced per combination of ranks
+    pub fn legal_plays(rules: &Rules, cards: &[Card], last_play: &Self) -> Vec<Self> {
+        // group cards by rank
+        let mut ranks: [Vec<usize>; 15] = Default::default();
+        for card in cards {
+            ranks[card.rank()].push(card.0);
+        }
+        let cnt = |r: usize| ranks[r].len();
+
+        // only generate hands of the right shape unless leading
+        // bombs and rockets are always generated
+        let wants = |name: HandName, len: usize| {
+            last_play.is_pass() || (last_play.kind.name == name && last_play.kind.mult == len)
+        };
+
+        let mut plays = Vec::new();
+        // picks contains pairs of (rank, number of cards)
+        let mut add = |picks: &[(usize, usize)]| {
+            let mut hand: Vec<usize> = picks
+                .iter()
+                .flat_map(|&(r, n)| ranks[r][..n].iter().copied())
+                .collect();
+            hand.sort();
+            if let Ok(hand) = Self::new(rules, hand) {
+                if hand.can_play(last_play, rules).is_ok() {
+                    plays.push(hand);
+                }
+            }
+        };
+
+        // chains of singles, pairs and triples, with kickers for triples
+        for (width, name) in [
+            (1, HandName::Single),
+            (2, HandName::Pair),
+            (3, HandName::Triple),
+        ] {
+            let min_len = rules.min_chain[width - 1];
+            for len in (1..=12).filter(|&len| len == 1 || len >= min_len) {
+                // note chain cannot include 2
+                let last_start = if len == 1 { 14 } else { 12 - len };
+                for start in 0..=last_start {
+                    let chain = start..start + len;
+                    if !chain.clone().all(|r| cnt(r) >= width) {
+                        continue;
+                    }
+                    let body: Vec<_> = chain.clone().map(|r| (r, width)).collect();
+                    if wants(name, len) {
+                        add(&body);
+                    }
+                    if width != 3 {
+                        continue;
+                    }
+
+                    for (kicker, name) in [(1, HandName::TripleSingle), (2, HandName::TriplePair)] {
+                        if !wants(name, len) {
+                            continue;
+                        }
+                        let options: Vec<_> = (0..15)
+                            .filter(|r| !chain.contains(r) && cnt(*r) >= kicker)
+                            .collect();
+                        for kickers in choose(&options, len) {
+                            let mut picks = body.clone();
+                            picks.extend(kickers.into_iter().map(|r| (r, kicker)));
+                            add(&picks);
+                        }
+                    }
+                }
+            }
+        }
+
+        // quads with kickers, which may beat anything like a bomb
+        for (kicker, name) in [(1, HandName::QuadSingle), (2, HandName::QuadPair)] {
+            if !wants(name, 1) && !rules.quad_kicker_bomb {
+                continue;
+            }
+            for quad in (0..13).filter(|&r| cnt(r) >= 4) {
+                let options: Vec<_> = (0..15).filter(|&r| r != quad && cnt(r) >= kicker).collect();
+                for kickers in choose(&options, 2) {
+                    let mut picks = vec![(quad, 4)];
+                    picks.extend(kickers.into_iter().map(|r| (r, kicker)));
+                    add(&picks);
+                }
+            }
+        }
+
+        // bombs of every size, then rockets
+        for size in 4..=8 {
+            for r in (0..13).filter(|&r| cnt(r) >= size) {
+                add(&[(r, size)]);
+            }
+        }
+        for n in 1..=2 {
+            if cnt(13) >= n && cnt(14) >= n {
+                add(&[(13, n), (14, n)]);
+            }
+        }
+
+        plays
+    }
+}
+
+// all subsets of size k, in lexicographic order
+fn choose(items: &[usize], k: usize) -> Vec<Vec<usize>> {
+    if k == 0 {
+        return vec![Vec::new()];
+    }
+    if items.len() < k {
+        return Vec::new();
+    }
+    let mut subsets: Vec<Vec<usize>> = choose(&items[1..], k - 1)
+        .into_iter()
+        .map(|mut rest| {
+            rest.insert(0, items[0]);
+            rest
+        })
+        .collect();
+    subsets.extend(choose(&items[1..], k));
+    subsets
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn hand(players: usize, cards: Vec<usize>) -> Result<Hand, String> {
+        Hand::new(&Rules::standard(players), cards)
+    }
 
     #[test]
     fn create_pass() {
-        let h = Hand::new(3, vec![]).unwrap();
+        let h = hand(3, vec![]).unwrap();
         assert!(h.is_pass());
     }
 
     #[test]
     fn create_single() {
-        let h = Hand::new(3, vec![10]).unwrap();
+        let h = hand(3, vec![10]).unwrap();
         assert_eq!(h.kind.name, HandName::Single);
         assert_eq!(h.kind.mult, 1);
 
-        let sj = Hand::new(3, vec![52]).unwrap();
-        let bj = Hand::new(3, vec![53]).unwrap();
-        assert!(sj.can_play(&h).is_ok());
-        assert!(bj.can_play(&sj).is_ok());
+        let sj = hand(3, vec![52]).unwrap();
+        let bj = hand(3, vec![53]).unwrap();
+        assert!(sj.can_play(&h, &Rules::standard(3)).is_ok());
+        assert!(bj.can_play(&sj, &Rules::standard(3)).is_ok());
     }
 
     #[test]
     fn create_straight() {
-        let h = Hand::new(3, vec![10, 14, 18, 22, 26]).unwrap();
+        let h = hand(3, vec![10, 14, 18, 22, 26]).unwrap();
         assert_eq!(h.kind.name, HandName::Single);
         assert_eq!(h.kind.mult, 5);
-        Hand::new(3, vec![10, 14, 18, 22]).unwrap_err();
+        hand(3, vec![10, 14, 18, 22]).unwrap_err();
     }
 
     #[test]
     fn create_pair() {
-        let h = Hand::new(3, vec![10, 11]).unwrap();
+        let h = hand(3, vec![10, 11]).unwrap();
         assert_eq!(h.kind.name, HandName::Pair);
         assert_eq!(h.kind.mult, 1);
 
-        let sjs = Hand::new(4, vec![52, 52]).unwrap();
+        let sjs = hand(4, vec![52, 52]).unwrap();
         assert_eq!(sjs.kind.name, HandName::Pair);
-        Hand::new(4, vec![52, 53]).unwrap_err();
+        hand(4, vec![52, 53]).unwrap_err();
     }
 
     #[test]
     fn create_pair_chain() {
-        let h = Hand::new(3, vec![10, 11, 12, 13, 16, 17]).unwrap();
+        let h = hand(3, vec![10, 11, 12, 13, 16, 17]).unwrap();
         assert_eq!(h.kind.name, HandName::Pair);
         assert_eq!(h.kind.mult, 3);
-        Hand::new(3, vec![10, 11, 12, 23]).unwrap_err();
+        hand(3, vec![10, 11, 12, 23]).unwrap_err();
     }
 
     #[test]
     fn create_triple() {
-        let h = Hand::new(3, vec![0, 1, 2]).unwrap();
+        let h = hand(3, vec![0, 1, 2]).unwrap();
         assert_eq!(h.kind.name, HandName::Triple);
         assert_eq!(h.kind.mult, 1);
-        Hand::new(4, vec![52, 52, 53]).unwrap_err();
+        hand(4, vec![52, 52, 53]).unwrap_err();
     }
 
     #[test]
     fn create_triple_chain() {
-        let h = Hand::new(3, vec![0, 1, 2, 4, 5, 6]).unwrap();
+        let h = hand(3, vec![0, 1, 2, 4, 5, 6]).unwrap();
         assert_eq!(h.kind.name, HandName::Triple);
         assert_eq!(h.kind.mult, 2);
-        Hand::new(3, vec![0, 1, 2, 8, 9, 10]).unwrap_err();
+        hand(3, vec![0, 1, 2, 8, 9, 10]).unwrap_err();
     }
 
     #[test]
     fn create_triple_single() {
-        let h = Hand::new(3, vec![0, 1, 2, 10]).unwrap();
+        let h = hand(3, vec![0, 1, 2, 10]).unwrap();
         assert_eq!(h.kind.name, HandName::TripleSingle);
         assert_eq!(h.kind.mult, 1);
-        Hand::new(4, vec![0, 1, 2, 10]).unwrap_err();
-        Hand::new(3, vec![0, 1, 2, 10, 12]).unwrap_err();
+        hand(4, vec![0, 1, 2, 10]).unwrap_err();
+        hand(3, vec![0, 1, 2, 10, 12]).unwrap_err();
     }
     #[test]
     fn create_triple_single_chain() {
-        let h = Hand::new(3, vec![0, 1, 2, 4, 5, 6, 10, 12]).unwrap();
+        let h = hand(3, vec![0, 1, 2, 4, 5, 6, 10, 12]).unwrap();
         assert_eq!(h.kind.name, HandName::TripleSingle);
         assert_eq!(h.kind.mult, 2);
-        Hand::new(3, vec![0, 1, 2, 4, 5, 6, 10, 11]).unwrap_err();
+        hand(3, vec![0, 1, 2, 4, 5, 6, 10, 11]).unwrap_err();
     }
 
     #[test]
     fn create_triple_pair() {
-        let h = Hand::new(3, vec![0, 1, 2, 10, 11]).unwrap();
+        let h = hand(3, vec![0, 1, 2, 10, 11]).unwrap();
         assert_eq!(h.kind.name, HandName::TriplePair);
         assert_eq!(h.kind.mult, 1);
-        Hand::new(4, vec![0, 1, 2, 10, 11]).unwrap();
-        Hand::new(4, vec![0, 1, 2, 52, 53]).unwrap_err();
+        hand(4, vec![0, 1, 2, 10, 11]).unwrap();
+        hand(4, vec![0, 1, 2, 52, 53]).unwrap_err();
     }
 
     #[test]
     fn create_triple_pair_chain() {
-        let chain = Hand::new(3, vec![0, 1, 2, 4, 5, 6, 10, 11, 12, 13]).unwrap();
+        let chain = hand(3, vec![0, 1, 2, 4, 5, 6, 10, 11, 12, 13]).unwrap();
         assert_eq!(chain.kind.name, HandName::TriplePair);
         assert_eq!(chain.kind.mult, 2);
     }
 
     #[test]
     fn create_quad_single() {
-        let h = Hand::new(3, vec![0, 1, 2, 3, 10, 12]).unwrap();
+        let h = hand(3, vec![0, 1, 2, 3, 10, 12]).unwrap();
         assert_eq!(h.kind.name, HandName::QuadSingle);
         assert_eq!(h.kind.mult, 1);
-        Hand::new(4, vec![0, 1, 2, 3, 10, 12]).unwrap_err();
+        hand(4, vec![0, 1, 2, 3, 10, 12]).unwrap_err();
     }
 
     #[test]
     fn create_quad_pair() {
-        let h = Hand::new(3, vec![0, 1, 2, 3, 10, 11, 12, 13]).unwrap();
+        let h = hand(3, vec![0, 1, 2, 3, 10, 11, 12, 13]).unwrap();
         assert_eq!(h.kind.name, HandName::QuadPair);
         assert_eq!(h.kind.mult, 1);
-        Hand::new(4, vec![0, 1, 2, 3, 10, 11, 12, 13]).unwrap_err();
+        hand(4, vec![0, 1, 2, 3, 10, 11, 12, 13]).unwrap_err();
     }
 
     #[test]
     fn create_bomb() {
-        let h = Hand::new(3, vec![0, 1, 2, 3]).unwrap();
+        let h = hand(3, vec![0, 1, 2, 3]).unwrap();
         assert_eq!(h.kind.name, HandName::Bomb);
         assert_eq!(h.kind.mult, 4);
-        assert!(h.is_double(3));
+        assert!(h.is_double(&Rules::standard(3)));
 
-        let h = Hand::new(4, vec![0, 0, 1, 2, 3]).unwrap();
+        let h = hand(4, vec![0, 0, 1, 2, 3]).unwrap();
         assert_eq!(h.kind.name, HandName::Bomb);
         assert_eq!(h.kind.mult, 5);
-        assert!(!h.is_double(4));
+        assert!(!h.is_double(&Rules::standard(4)));
 
-        let h = Hand::new(4, vec![0, 0, 1, 1, 2, 3]).unwrap();
-        assert!(h.is_double(4));
+        let h = hand(4, vec![0, 0, 1, 1, 2, 3]).unwrap();
+        assert!(h.is_double(&Rules::standard(4)));
     }
 
     #[test]
     fn create_rocket() {
-        let h = Hand::new(3, vec![52, 53]).unwrap();
+        let h = hand(3, vec![52, 53]).unwrap();
         assert_eq!(h.kind.name, HandName::Rocket);
-        assert!(h.is_double(3));
-        Hand::new(4, vec![52, 53]).unwrap_err();
+        assert!(h.is_double(&Rules::standard(3)));
+        hand(4, vec![52, 53]).unwrap_err();
 
-        let h = Hand::new(4, vec![52, 52, 53, 53]).unwrap();
+        let h = hand(4, vec![52, 52, 53, 53]).unwrap();
         assert_eq!(h.kind.name, HandName::Rocket);
-        assert!(h.is_double(4));
+        assert!(h.is_double(&Rules::standard(4)));
+    }
+
+    #[test]
+    fn house_rules() {
+        let rules = Rules {
+            triple_single: false,
+            high_kickers: false,
+            quad_kicker_bomb: true,
+            double_bomb_size: 5,
+            min_chain: [3, 2, 2],
+            ..Rules::standard(3)
+        };
+        let new = |cards| Hand::new(&rules, cards);
+
+        new(vec![0, 1, 2, 10]).unwrap_err();
+        new(vec![0, 1, 2, 48, 49]).unwrap_err();
+        new(vec![0, 1, 2, 44, 45]).unwrap();
+        assert_eq!(new(vec![0, 4, 8]).unwrap().kind.mult, 3);
+        assert_eq!(new(vec![0, 1, 4, 5]).unwrap().kind.mult, 2);
+
+        // quads with kickers beat other hands but not bombs, and bombs of 4 no longer double
+        let quad = new(vec![0, 1, 2, 3, 10, 12]).unwrap();
+        let pair = new(vec![40, 41]).unwrap();
+        let bomb = new(vec![4, 5, 6, 7]).unwrap();
+        quad.can_play(&pair, &rules).unwrap();
+        quad.can_play(&bomb, &rules).unwrap_err();
+        bomb.can_play(&quad, &rules).unwrap();
+        quad.can_play(&pair, &Rules::standard(3)).unwrap_err();
+        assert!(!bomb.is_double(&rules));
+        assert!(!quad.is_double(&rules));
+    }
+
+    #[test]
+    fn deal_seeded() {
+        let deal = |seed| Hand::deal_hands(4, &mut StdRng::seed_from_u64(seed)).unwrap();
+        assert_eq!(deal(7), deal(7));
+        assert_ne!(deal(7), deal(8));
+
+        let hands = deal(7);
+        assert_eq!(hands.len(), 5);
+        assert_eq!(hands[4].len(), 8);
+        assert_eq!(hands.iter().map(Vec::len).sum::<usize>(), 108);
+    }
+
+    #[test]
+    fn deal_unsupported() {
+        let deal = Hand::deal_hands(5, &mut StdRng::seed_from_u64(7));
+        assert_eq!(deal.unwrap_err(), "games are for 3 or 4 players, not 5");
+    }
+
+    fn plays(players: usize, cards: Vec<usize>, last_play: Vec<usize>) -> Vec<Vec<usize>> {
+        let cards: Vec<Card> = cards.into_iter().map(Card).collect();
+        let rules = Rules::standard(players);
+        let last_play = Hand::new(&rules, last_play).unwrap();
+        Hand::legal_plays(&rules, &cards, &last_play)
+            .into_iter()
+            .map(|h| h.cards.iter().map(|c| c.0).collect())
+            .collect()
+    }
+
+    #[test]
+    fn legal_plays_lead() {
+        let p = plays(3, vec![0, 1, 4, 52, 53], vec![]);
+        assert_eq!(
+            p,
+            vec![
+                vec![0],
+                vec![4],
+                vec![52],
+                vec![53],
+                vec![0, 1],
+                vec![52, 53]
+            ]
+        );
+    }
+
+    #[test]
+    fn legal_plays_follow() {
+        // pairs higher than 4s, then the bomb
+        let p = plays(3, vec![0, 1, 8, 9, 20, 21, 22, 23], vec![4, 5]);
+        assert_eq!(p, vec![vec![8, 9], vec![20, 21], vec![20, 21, 22, 23]]);
+
+        // only a higher bomb beats a bomb
+        let p = plays(3, vec![0, 1, 2, 3, 8, 9, 10, 11], vec![4, 5, 6, 7]);
+        assert_eq!(p, vec![vec![8, 9, 10, 11]]);
+
+        // nothing beats a rocket
+        assert!(plays(3, vec![0, 1, 2, 3], vec![52, 53]).is_empty());
+    }
+
+    #[test]
+    fn legal_plays_chains() {
+        let hand = vec![0, 4, 8, 12, 16, 20, 24];
+        let p = plays(3, hand.clone(), vec![1, 5, 9, 13, 17]);
+        assert_eq!(p, vec![vec![4, 8, 12, 16, 20], vec![8, 12, 16, 20, 24]]);
+        assert!(plays(3, hand, vec![1, 5, 9, 13, 17, 21]).len() == 1);
+    }
+
+    #[test]
+    fn legal_plays_kickers() {
+        // airplane with wings
+        let p = plays(
+            3,
+            vec![4, 5, 6, 8, 9, 10, 20, 24, 28],
+            vec![0, 1, 2, 4, 5, 6, 12, 16],
+        );
+        assert_eq!(p.len(), 3);
+        assert!(p
+            .iter()
+            .all(|h| h.len() == 8 && h[..6] == [4, 5, 6, 8, 9, 10]));
+
+        // quad with kickers
+        let p = plays(3, vec![4, 5, 6, 7, 8, 12, 16], vec![0, 1, 2, 3, 20, 24]);
+        assert_eq!(p.len(), 4);
+        assert_eq!(p[0], vec![4, 5, 6, 7, 8, 12]);
+        assert_eq!(p[3], vec![4, 5, 6, 7]);
+        let p = plays(
+            3,
+            vec![4, 5, 6, 7, 8, 9, 12, 13],
+            vec![0, 1, 2, 3, 20, 21, 24, 25],
+        );
+        assert_eq!(p, vec![vec![4, 5, 6, 7, 8, 9, 12, 13], vec![4, 5, 6, 7]]);
+    }
+
+    #[test]
+    fn legal_plays_double_deck() {
+        // four player mode has larger bombs and a four joker rocket
+        let cards = vec![0, 0, 1, 1, 2, 52, 52, 53, 53];
+        let p = plays(4, cards.clone(), vec![4, 5, 6, 7]);
+        assert_eq!(p, vec![vec![0, 0, 1, 1, 2], vec![52, 52, 53, 53]]);
+
+        // triple with pair but no triple with single
+        let p = plays(4, cards, vec![]);
+        assert!(p.contains(&vec![0, 0, 1, 52, 52]));
+        assert!(!p.iter().any(|h| h.len() == 4 && h[0] == 0 && h[3] != 1));
+        assert!(!p.contains(&vec![52, 53]));
     }
 }
@@ -1,60 +1,191 @@
 use crate::card::{self, Card, Hand};
-use serde_json::Value;
+use crate::rules::{BidMode, Rules};
+use crate::strategy::{Action, View};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum Event {
+    Bid { idx: usize, val: usize },
+    Play { idx: usize, cards: Vec<Card> },
+    Pass { idx: usize },
+    Double { idx: usize, val: bool },
+    Reveal { idx: usize },
+}
+
+// a game is rebuilt by replaying its events on the seeded deal
+#[derive(Deserialize, Serialize)]
+pub struct SavedGame {
+    players: usize,
+    seed: u64,
+    rules: Rules,
+    events: Vec<Event>,
+}
 
 #[derive(Default)]
 pub struct Game {
     players: usize,
+    seed: u64,
+    rules: Rules,
     cards: Vec<Vec<Card>>, // last element contains hidden cards
+    dealt: Vec<Vec<Card>>,
     turn: usize,
     bid: usize,
+    bids: usize,   // number of bids made this deal
+    caller: usize, // first to call in rob mode
     mult: usize,
+    doubling: bool, // peasants may double after bidding, then the landlord may redouble
+    doubles: Vec<usize>, // multiplier between the landlord and each peasant
+    revealed: Vec<bool>, // open hands double the stakes
     landlord: usize,
     last_idx: usize,
     last_play: Hand,
     passes: usize,
     winner: Option<usize>,
     played_mask: usize, // 3 bits representing landlord play, peasants play, landlord play 2
+    bombs: Vec<[usize; 2]>, // bombs and rockets played by each player
+    events: Vec<Event>,
+    deadline: Option<u64>, // unix time in ms when the current turn runs out
 }
 
 impl Game {
-    pub fn new(players: usize) -> Self {
-        Self {
+    // a random seed that fits in a javascript number
+    pub fn random_seed() -> u64 {
+        rand::random_range(..1 << 53)
+    }
+
+    // seed of the deal that replaces this one when no one bids
+    // drawn from its own stream, since games are often seeded one after another
+    pub fn redeal_seed(&self) -> u64 {
+        const REDEAL: u64 = 0x9e37_79b9_7f4a_7c15;
+        StdRng::seed_from_u64(self.seed ^ REDEAL).random_range(..1 << 53)
+    }
+
+    // the seed decides the deal and who bids first
+    // standard rules are used unless others are given
+    // fails unless there are 3 or 4 players
+    pub fn new(players: usize, seed: u64) -> Result<Self, String> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let cards = Hand::deal_hands(players, &mut rng)?;
+        Ok(Self {
             players,
-            cards: Hand::deal_hands(players),
-            turn: rand::random_range(..players),
+            seed,
+            rules: Rules::standard(players),
+            dealt: cards.clone(),
+            cards,
+            turn: rng.random_range(..players),
             bid: 0,
+            bids: 0,
+            caller: 0,
             mult: 1,
+            doubling: false,
+            doubles: vec![1; players],
+            revealed: vec![false; players],
             landlord: players,
             last_play: Hand::PASS,
             last_idx: 0,
             passes: 0,
             winner: None,
             played_mask: 0,
+            bombs: vec![[0; 2]; players],
+            events: Vec::new(),
+            deadline: None,
+        })
+    }
+
+    pub fn with_rules(mut self, rules: Rules) -> Self {
+        self.rules = rules;
+        self
+    }
+
+    pub fn save(&self) -> SavedGame {
+        SavedGame {
+            players: self.players,
+            seed: self.seed,
+            rules: self.rules.clone(),
+            events: self.events.clone(),
+        }
+    }
+
+    pub fn load(saved: SavedGame) -> Result<Self, String> {
+        let mut game = Self::new(saved.players, saved.seed)?.with_rules(saved.rules);
+        for event in saved.events {
+            match event {
+                Event::Bid { idx, val } => {
+                    game.bid(idx, val)?;
+                }
+                Event::Play { idx, cards } => {
+                    let cards = cards.iter().map(Card::value).collect();
+                    let hand = Hand::new(&game.rules, cards)?;
+                    game.play(idx, hand)?;
+                }
+                Event::Pass { idx } => game.play(idx, Hand::PASS)?,
+                Event::Double { idx, val } => game.double(idx, val)?,
+                Event::Reveal { idx } => game.reveal(idx)?,
+            }
         }
+        Ok(game)
     }
 
     // getter functions
     pub fn players(&self) -> usize {
         self.players
     }
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+    pub fn rules(&self) -> &Rules {
+        &self.rules
+    }
+    pub fn high_bid(&self) -> usize {
+        self.bid
+    }
+    // what the landlord bid, where each call or rob counts one level in rob mode
+    pub fn bid_level(&self) -> usize {
+        match self.rules.bid_mode {
+            BidMode::Points => self.bid,
+            BidMode::Rob => self
+                .events
+                .iter()
+                .filter(|event| matches!(event, Event::Bid { val: 1, .. }))
+                .count(),
+        }
+    }
     pub fn landlord(&self) -> usize {
         self.landlord
     }
     pub fn playing(&self) -> bool {
         self.landlord != self.players
     }
+    pub fn doubling(&self) -> bool {
+        self.doubling
+    }
     pub fn score_delta(&self) -> usize {
         self.mult * self.bid
     }
+    pub fn domination(&self) -> bool {
+        self.winner.is_some() && self.played_mask < 6
+    }
     pub fn turn(&self) -> usize {
         self.turn
     }
     pub fn winner(&self) -> Option<usize> {
         self.winner
     }
+    pub fn bombs(&self) -> &[[usize; 2]] {
+        &self.bombs
+    }
     pub fn played_mask(&self) -> usize {
         self.played_mask
     }
+    pub fn deadline(&self) -> Option<u64> {
+        self.deadline
+    }
+
+    pub fn set_deadline(&mut self, deadline: Option<u64>) {
+        self.deadline = deadline;
+    }
 
     // Ok(true) means redeal
     pub fn bid(&mut self, idx: usize, val: usize) -> Result<bool, String> {
@@ -66,11 +197,20 @@ impl Game {
         if self.turn != idx {
             return Err("not your turn".to_string());
         }
+        match self.rules.bid_mode {
+            BidMode::Points => self.bid_points(idx, val),
+            BidMode::Rob => self.bid_rob(idx, val),
+        }
+    }
 
+    fn bid_points(&mut self, idx: usize, val: usize) -> Result<bool, String> {
         // bid of 0 denotes pass
         if val == 0 {
             self.passes += 1;
         } else {
+            if val > self.rules.max_bid {
+                return Err(format!("bid must be at most {}", self.rules.max_bid));
+            }
             if val <= self.bid {
                 return Err("must bid higher than previous bid".to_string());
             }
@@ -78,17 +218,10 @@ impl Game {
             self.bid = val;
             self.last_idx = idx;
         }
+        self.events.push(Event::Bid { idx, val });
 
-        // end bidding phase
-        if (self.passes == self.players - 1 && self.bid > 0) || self.bid == 3 {
-            self.passes = 0;
-            self.landlord = self.last_idx;
-            self.turn = self.landlord;
-
-            // landlord receives new cards
-            let hidden = self.cards[self.players].clone();
-            self.cards[self.landlord].extend(hidden);
-            self.cards[self.landlord].sort();
+        if (self.passes == self.players - 1 && self.bid > 0) || self.bid == self.rules.max_bid {
+            self.end_bidding();
         } else if self.passes == self.players {
             // no one bid, so redeal
             return Ok(true);
@@ -98,11 +231,124 @@ impl Game {
         Ok(false)
     }
 
-    pub fn play(&mut self, idx: usize, hand: Hand) -> Result<(), String> {
+    fn bid_rob(&mut self, idx: usize, val: usize) -> Result<bool, String> {
+        if val > 1 {
+            return Err("bid 1 to call or rob the landlord".to_string());
+        }
+        // everyone has had a turn, so this is the caller's last word
+        let last_word = self.bids == self.players;
+        if val == 1 {
+            if self.bid == 0 {
+                self.bid = 1;
+                self.caller = idx;
+            } else {
+                self.mult *= 2;
+            }
+            self.last_idx = idx;
+        }
+        self.bids += 1;
+        self.events.push(Event::Bid { idx, val });
+
+        if last_word {
+            self.end_bidding();
+        } else if self.bids == self.players {
+            if self.bid == 0 {
+                // no one called, so redeal
+                return Ok(true);
+            } else if self.last_idx == self.caller {
+                // no one robbed
+                self.end_bidding();
+            } else {
+                self.turn = self.caller;
+            }
+        } else {
+            self.turn = (idx + 1) % self.players;
+        }
+        Ok(false)
+    }
+
+    fn end_bidding(&mut self) {
+        self.passes = 0;
+        self.landlord = self.last_idx;
+        self.turn = self.landlord;
+
+        // landlord receives new cards
+        let hidden = self.cards[self.players].clone();
+        self.cards[self.landlord].extend(hidden);
+        self.cards[self.landlord].sort();
+
+        // peasants decide whether to double in turn order
+        if self.rules.doubling {
+            self.doubling = true;
+            self.turn = (self.landlord + 1) % self.players;
+        }
+    }
+
+    // true doubles the stakes, for the landlord only against peasants who doubled
+    pub fn double(&mut self, idx: usize, val: bool) -> Result<(), String> {
+        if !self.doubling {
+            return Err("not doubling".to_string());
+        }
+        if self.turn != idx {
+            return Err("not your turn".to_string());
+        }
+
+        if val {
+            if idx == self.landlord {
+                for mult in &mut self.doubles {
+                    if *mult > 1 {
+                        *mult *= 2;
+                    }
+                }
+            } else {
+                self.doubles[idx] *= 2;
+            }
+        }
+        self.events.push(Event::Double { idx, val });
+
+        // the landlord only decides if a peasant doubled
+        let next = (idx + 1) % self.players;
+        if idx == self.landlord || (next == self.landlord && self.doubles.iter().all(|&m| m == 1)) {
+            self.doubling = false;
+            self.turn = self.landlord;
+        } else {
+            self.turn = next;
+        }
+        Ok(())
+    }
+
+    // show a hand to everyone, allowed until the first card is played
+    // the landlord revealing doubles every stake, a peasant only their own
+    pub fn reveal(&mut self, idx: usize) -> Result<(), String> {
+        if !self.rules.reveal {
+            return Err("open hands are not allowed".to_string());
+        }
+        if idx >= self.players {
+            return Err("not a player".to_string());
+        }
+        if self.revealed[idx] {
+            return Err("hand already revealed".to_string());
+        }
+        if self
+            .events
+            .iter()
+            .any(|event| matches!(event, Event::Play { .. }))
+        {
+            return Err("cards have been played".to_string());
+        }
+        self.revealed[idx] = true;
+        self.events.push(Event::Reveal { idx });
+        Ok(())
+    }
+
+    fn check_play_turn(&self, idx: usize) -> Result<(), String> {
         // check phase
         if !self.playing() {
             return Err("still bidding".to_string());
         }
+        if self.doubling {
+            return Err("still doubling".to_string());
+        }
         if self.winner.is_some() {
             return Err("game finished".to_string());
         }
@@ -110,6 +356,11 @@ impl Game {
         if self.turn != idx {
             return Err("not your turn".to_string());
         }
+        Ok(())
+    }
+
+    pub fn play(&mut self, idx: usize, hand: Hand) -> Result<(), String> {
+        self.check_play_turn(idx)?;
 
         // check hand exists in cards
         let mut i = 0;
@@ -131,13 +382,14 @@ impl Game {
             if self.last_play.is_pass() || self.passes == self.players - 1 {
                 return Err("cannot pass".to_string());
             }
+            self.events.push(Event::Pass { idx });
             self.passes += 1;
             if self.passes == self.players - 1 {
                 self.passes = 0;
                 self.last_play = Hand::PASS;
             }
         } else {
-            hand.can_play(&self.last_play)?;
+            hand.can_play(&self.last_play, &self.rules)?;
             // remove cards
             for card in hand.cards() {
                 let pos = self.cards[idx]
@@ -151,9 +403,12 @@ impl Game {
             if self.cards[idx].is_empty() {
                 self.winner = Some(idx);
             }
-            if hand.is_double(self.players) {
+            if hand.is_double(&self.rules) {
                 self.mult *= 2;
             }
+            if hand.is_bomb() {
+                self.bombs[idx][hand.is_rocket() as usize] += 1;
+            }
             self.played_mask |= if idx == self.landlord {
                 if self.played_mask & 1 != 0 {
                     4
@@ -165,6 +420,10 @@ impl Game {
             };
 
             // update game state
+            self.events.push(Event::Play {
+                idx,
+                cards: hand.cards().clone(),
+            });
             self.passes = 0;
             self.last_idx = idx;
             self.last_play = hand;
@@ -174,6 +433,125 @@ impl Game {
         Ok(())
     }
 
+    // score change for each player once the game is finished
+    pub fn score_deltas(&self) -> Vec<i32> {
+        let mut delta = self.score_delta() as i32;
+        if self.domination() {
+            delta *= 2;
+        }
+        if self.winner != Some(self.landlord) {
+            delta *= -1;
+        }
+
+        // each peasant settles with the landlord separately
+        let reveal = |i: usize| if self.revealed[i] { 2 } else { 1 };
+        let mut deltas: Vec<i32> = (0..self.players)
+            .map(|i| -delta * (self.doubles[i] * reveal(i) * reveal(self.landlord)) as i32)
+            .collect();
+        deltas[self.landlord] = 0;
+        deltas[self.landlord] = -deltas.iter().sum::<i32>();
+        deltas
+    }
+
+    // move made for a player who ran out of time
+    // passes if allowed, otherwise leads the lowest single
+    pub fn auto_action(&self) -> Action {
+        if !self.playing() {
+            return Action::Bid(0);
+        }
+        if self.doubling {
+            return Action::Double(false);
+        }
+        if !self.last_play.is_pass() {
+            return Action::Play(Hand::PASS);
+        }
+        let lowest = self.cards[self.turn][0].value();
+        Action::Play(Hand::new(&self.rules, vec![lowest]).expect("single is always valid"))
+    }
+
+    pub fn legal_plays(&self, idx: usize) -> Vec<Hand> {
+        Hand::legal_plays(&self.rules, &self.cards[idx], &self.last_play)
+    }
+
+    // positions in the hand of the nth suggested play, empty means pass
+    pub fn hint(&self, idx: usize, n: usize) -> Result<Vec<usize>, String> {
+        self.check_play_turn(idx)?;
+
+        let plays = self.legal_plays(idx);
+        if plays.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // both are sorted, so match cards left to right
+        let cards = &self.cards[idx];
+        let mut positions = Vec::new();
+        let mut i = 0;
+        for card in plays[n % plays.len()].cards() {
+            while cards[i] != *card {
+                i += 1;
+            }
+            positions.push(i);
+            i += 1;
+        }
+        Ok(positions)
+    }
+
+    // what player idx can see
+    pub fn view(&self, idx: usize) -> View {
+        View {
+            idx,
+            players: self.players,
+            rules: self.rules.clone(),
+            cards: self.cards[idx].clone(),
+            cards_left: self.cards[..self.players].iter().map(Vec::len).collect(),
+            bid: self.bid,
+            landlord: self.playing().then_some(self.landlord),
+            doubling: self.doubling,
+            revealed: self.revealed_hands(),
+            bonus: if self.playing() {
+                self.cards[self.players].clone()
+            } else {
+                Vec::new()
+            },
+            last_idx: self.last_idx,
+            last_play: self.last_play.clone(),
+            passes: self.passes,
+            played: self
+                .events
+                .iter()
+                .flat_map(|event| match event {
+                    Event::Play { cards, .. } => cards.as_slice(),
+                    _ => &[],
+                })
+                .copied()
+                .collect(),
+        }
+    }
+
+    // everything needed to step through the game from the deal
+    pub fn replay(&self) -> Value {
+        json!({
+            "seed": self.seed,
+            "rules": self.rules,
+            "hands": self.dealt[..self.players],
+            "bonus": self.dealt[self.players],
+            "landlord": self.playing().then_some(self.landlord),
+            "bid": self.bid,
+            "doubles": self.doubles,
+            "revealed": self.revealed,
+            "events": self.events,
+            "winner": self.winner,
+            "deltas": self.winner.map(|_| self.score_deltas()),
+        })
+    }
+
+    // current cards of players who revealed their hand
+    pub fn revealed_hands(&self) -> Vec<Option<Vec<Card>>> {
+        (0..self.players)
+            .map(|i| self.revealed[i].then(|| self.cards[i].clone()))
+            .collect()
+    }
+
     pub fn landlord_bonus(&self) -> String {
         card::join(&self.cards[self.players])
     }
@@ -199,6 +577,15 @@ impl Game {
             ),
         );
         game.insert("last_idx".to_string(), Value::from(self.last_idx));
+        if self.revealed.contains(&true) {
+            game.insert(
+                "revealed".to_string(),
+                serde_json::to_value(self.revealed_hands()).unwrap(),
+            );
+        }
+        if let Some(deadline) = self.deadline {
+            game.insert("deadline".to_string(), Value::from(deadline));
+        }
 
         if self.playing() {
             game.insert(
@@ -207,13 +594,233 @@ impl Game {
             );
             game.insert("landlord".to_string(), Value::from(self.landlord));
             game.insert("bonus".to_string(), Value::from(self.landlord_bonus()));
+            if self.rules.doubling {
+                game.insert("doubles".to_string(), Value::from(self.doubles.clone()));
+            }
         }
 
         // finished
         if let Some(winner) = self.winner {
             game.insert("winner".to_string(), Value::from(winner));
+            game.insert("seed".to_string(), Value::from(self.seed));
         }
 
         Value::from(game)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strategy::{Action, Greedy, Strategy};
+
+    #[test]
+    fn save_and_load() {
+        let mut rng = rand::rng();
+        let mut game = Game::new(3, 42).unwrap();
+        for _ in 0..20 {
+            let idx = game.turn();
+            match Greedy.act(&game.view(idx), &mut rng) {
+                Action::Bid(val) => assert!(!game.bid(idx, val).unwrap()),
+                Action::Double(val) => game.double(idx, val).unwrap(),
+                Action::Play(hand) => game.play(idx, hand).unwrap(),
+            }
+        }
+
+        let loaded = Game::load(game.save()).unwrap();
+        assert_eq!(loaded.serialize(), game.serialize());
+        assert_eq!(loaded.bombs(), game.bombs());
+        for idx in 0..3 {
+            assert_eq!(loaded.serialize_cards(idx), game.serialize_cards(idx));
+        }
+    }
+
+    #[test]
+    fn redeal_seeds() {
+        let game = Game::new(3, 5).unwrap();
+        assert_eq!(game.redeal_seed(), Game::new(3, 5).unwrap().redeal_seed());
+        // never the deal of a nearby game
+        for seed in 0..1000 {
+            let redeal = Game::new(3, seed).unwrap().redeal_seed();
+            assert!(redeal.abs_diff(seed) > 1000);
+        }
+    }
+
+    #[test]
+    fn rob_the_landlord() {
+        let rob = Rules {
+            bid_mode: BidMode::Rob,
+            ..Rules::standard(3)
+        };
+        let first = Game::new(3, 5).unwrap().turn();
+        let next = |n: usize| (first + n) % 3;
+
+        // no one robs, so the caller is landlord
+        let mut called = Game::new(3, 5).unwrap().with_rules(rob.clone());
+        assert!(called.bid(first, 2).is_err());
+        called.bid(first, 0).unwrap();
+        called.bid(next(1), 1).unwrap();
+        called.bid(next(2), 0).unwrap();
+        assert_eq!(called.landlord(), next(1));
+        assert_eq!(called.score_delta(), 1);
+        assert_eq!(called.bid_level(), 1);
+
+        // robbed twice, then the caller robs back with the last word
+        let mut robbed = Game::new(3, 5).unwrap().with_rules(rob.clone());
+        robbed.bid(first, 1).unwrap();
+        robbed.bid(next(1), 1).unwrap();
+        robbed.bid(next(2), 1).unwrap();
+        assert_eq!(robbed.turn(), first);
+        robbed.bid(first, 1).unwrap();
+        assert_eq!(robbed.landlord(), first);
+        assert_eq!(robbed.score_delta(), 8);
+        assert_eq!(robbed.bid_level(), 4);
+
+        // the caller lets the last robber keep it
+        let mut kept = Game::new(3, 5).unwrap().with_rules(rob.clone());
+        kept.bid(first, 1).unwrap();
+        kept.bid(next(1), 0).unwrap();
+        kept.bid(next(2), 1).unwrap();
+        kept.bid(first, 0).unwrap();
+        assert_eq!(kept.landlord(), next(2));
+        assert_eq!(kept.score_delta(), 2);
+        assert_eq!(Game::load(kept.save()).unwrap().landlord(), next(2));
+
+        // no one calls
+        let mut redeal = Game::new(3, 5).unwrap().with_rules(rob.clone());
+        assert!(!redeal.bid(first, 0).unwrap());
+        assert!(!redeal.bid(next(1), 0).unwrap());
+        assert!(redeal.bid(next(2), 0).unwrap());
+    }
+
+    #[test]
+    fn doubling_per_pairing() {
+        let doubling = Rules {
+            doubling: true,
+            ..Rules::standard(3)
+        };
+        let mut game = Game::new(3, 5).unwrap().with_rules(doubling.clone());
+        let landlord = game.turn();
+        let first = (landlord + 1) % 3;
+        let second = (landlord + 2) % 3;
+        game.bid(landlord, 3).unwrap();
+        assert!(game.doubling());
+        assert!(game.play(landlord, Hand::PASS).is_err());
+
+        // one peasant doubles and the landlord redoubles
+        game.double(first, true).unwrap();
+        game.double(second, false).unwrap();
+        game.double(landlord, true).unwrap();
+        assert!(!game.doubling());
+        assert_eq!(game.turn(), landlord);
+        let mut doubles = vec![1; 3];
+        doubles[first] = 4;
+        assert_eq!(game.doubles, doubles);
+
+        // landlord wins against both peasants
+        game.winner = Some(landlord);
+        game.played_mask = 7;
+        let deltas = game.score_deltas();
+        assert_eq!(deltas[first], -12);
+        assert_eq!(deltas[second], -3);
+        assert_eq!(deltas[landlord], 15);
+        assert_eq!(Game::load(game.save()).unwrap().doubles, game.doubles);
+
+        // the landlord is skipped if no one doubles
+        let mut game = Game::new(3, 5).unwrap().with_rules(doubling.clone());
+        game.bid(landlord, 3).unwrap();
+        game.double(first, false).unwrap();
+        game.double(second, false).unwrap();
+        assert!(!game.doubling());
+        assert_eq!(game.turn(), landlord);
+        assert_eq!(game.doubles, vec![1; 3]);
+    }
+
+    #[test]
+    fn reveal_doubles_stakes() {
+        let mut game = Game::new(3, 5).unwrap().with_rules(Rules {
+            reveal: true,
+            ..Rules::standard(3)
+        });
+        let landlord = game.turn();
+        let peasant = (landlord + 1) % 3;
+        game.reveal(landlord).unwrap();
+        assert!(game.reveal(landlord).is_err());
+        game.bid(landlord, 1).unwrap();
+        game.bid(peasant, 0).unwrap();
+        game.bid((landlord + 2) % 3, 0).unwrap();
+
+        // the landlord can see the bonus cards in the revealed hand
+        let state = game.serialize();
+        assert_eq!(state["revealed"][landlord], game.serialize_cards(landlord));
+        assert!(state["revealed"][peasant].is_null());
+
+        game.reveal(peasant).unwrap();
+        game.play(landlord, game.legal_plays(landlord)[0].clone())
+            .unwrap();
+        assert!(game.reveal((landlord + 2) % 3).is_err());
+
+        game.winner = Some(peasant);
+        game.played_mask = 7;
+        let deltas = game.score_deltas();
+        assert_eq!(deltas[peasant], 4);
+        assert_eq!(deltas[(landlord + 2) % 3], 2);
+        assert_eq!(deltas[landlord], -6);
+        assert_eq!(Game::load(game.save()).unwrap().revealed, game.revealed);
+        assert!(Game::new(3, 5).unwrap().reveal(0).is_err());
+    }
+
+    #[test]
+    fn hints_select_legal_plays() {
+        for players in [3, 4] {
+            let mut game = Game::new(players, 11).unwrap();
+            assert!(game.hint(game.turn(), 0).is_err());
+            game.bid(game.turn(), 3).unwrap();
+            assert!(game.hint((game.turn() + 1) % players, 0).is_err());
+
+            // play the hints through to the end of the game
+            let mut n = 0;
+            while game.winner().is_none() {
+                let idx = game.turn();
+                let plays = game.legal_plays(idx);
+                let cards = &game.cards[idx];
+                for (i, play) in plays.iter().enumerate() {
+                    let hint = game.hint(idx, i).unwrap();
+                    // positions are distinct even with two decks
+                    assert!(hint.windows(2).all(|w| w[0] < w[1]));
+                    let hinted: Vec<_> = hint.iter().map(|&i| cards[i]).collect();
+                    assert_eq!(&hinted, play.cards());
+                }
+                // the hints cycle
+                let hint = game.hint(idx, n).unwrap();
+                if !plays.is_empty() {
+                    assert_eq!(game.hint(idx, n + plays.len()).unwrap(), hint);
+                }
+                let values = hint.iter().map(|&i| cards[i].value()).collect();
+                let hand = Hand::new(&game.rules, values).unwrap();
+                game.play(idx, hand).unwrap();
+                n += 1;
+            }
+        }
+    }
+
+    #[test]
+    fn auto_action_finishes_game() {
+        for players in [3, 4] {
+            let mut game = Game::new(players, 7).unwrap().with_rules(Rules {
+                doubling: true,
+                ..Rules::standard(players)
+            });
+            assert!(matches!(game.auto_action(), Action::Bid(0)));
+            game.bid(game.turn(), 3).unwrap();
+            while game.winner().is_none() {
+                let idx = game.turn();
+                match game.auto_action() {
+                    Action::Bid(_) => panic!("bidding is over"),
+                    Action::Double(val) => game.double(idx, val).unwrap(),
+                    Action::Play(hand) => game.play(idx, hand).unwrap(),
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,6 @@
+// game engine shared by the server and the simulator
+pub mod card;
+pub mod game;
+pub mod rating;
+pub mod rules;
+pub mod strategy;
@@ -1,30 +1,45 @@
 use std::{
-    collections::HashMap,
-    time::{SystemTime, UNIX_EPOCH},
+    collections::{HashMap, HashSet},
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use serde_json::Value;
 use tokio::sync::broadcast;
+use tracing::warn;
 use uuid::Uuid;
 
-use crate::card::{self, Hand};
-use crate::game::Game;
+use crate::card::{self, Card, Hand};
+use crate::game::{Game, SavedGame};
+use crate::rating;
+use crate::rules::{BidMode, Rules};
+use crate::stats::{Record, StatsRef};
+use crate::strategy::{Action, Level, View};
 use crate::{AppError, User};
 
 #[derive(Debug, Deserialize)]
 pub enum ClientMsg {
     Chat(String),
-    Start,
+    Start(Option<u64>), // optional seed for the deal
     Bid(usize),
     Play(Vec<usize>),
+    Hint,
+    AddBot(Option<Level>),   // host only
+    Settings(SettingsPatch), // host only
+    Leave,
+    Kick(Kick), // host only
+    Double(bool),
+    Reveal,
 }
 
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Msg {
     text: String,
-    idx: usize, // player idx or 9 for game messages
+    idx: usize, // player idx, 9 for game messages or 10 for those waiting for a seat
     time: u64,
+    seq: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    name: Option<String>, // sender, none for game messages
 }
 
 #[derive(Clone, Debug, Serialize)]
@@ -33,41 +48,239 @@ pub enum ServerMsg {
     State(Value),
 }
 
+// numbered so clients can tell which messages they missed
 #[derive(Clone, Debug, Serialize)]
+pub struct Update {
+    pub seq: u64,
+    #[serde(flatten)]
+    pub msg: ServerMsg,
+}
+
+// lobby options only the host can change
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Settings {
+    seats: usize,            // 3 with one deck or 4 with two
+    rules: Option<Rules>,    // house rules, None for standard rules
+    turn_limit: Option<u64>, // seconds, None for no limit
+    private: bool,           // only joinable by code
+}
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            seats: 3,
+            rules: None,
+            turn_limit: None,
+            private: false,
+        }
+    }
+}
+
+// changes to the settings, missing fields are left as they are
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct SettingsPatch {
+    seats: Option<usize>,
+    #[serde(deserialize_with = "present")]
+    rules: Option<Option<Rules>>, // null for standard rules
+    #[serde(deserialize_with = "present")]
+    turn_limit: Option<Option<u64>>, // null for no limit
+    private: Option<bool>,
+}
+
+impl SettingsPatch {
+    pub fn seats(seats: usize) -> Self {
+        Self {
+            seats: Some(seats),
+            ..Default::default()
+        }
+    }
+}
+
+// tells a null field apart from a missing one
+fn present<'de, D, T>(de: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    T::deserialize(de).map(Some)
+}
+
+// how a public lobby shows up in the lobby list
+#[derive(Debug, Serialize)]
+pub struct Listing {
+    code: String,
+    players: Vec<String>,
+    seats: usize,
+    waiting: usize,
+    status: Status,
+    bid_mode: BidMode,
+    custom_rules: bool,
+    pub joinable: bool, // has a free seat
+    pub in_progress: bool,
+}
+
+// seat or place in the waiting queue to remove and whether its user may rejoin
+#[derive(Debug, Deserialize)]
+pub struct Kick {
+    idx: usize,
+    #[serde(default)]
+    waiting: bool,
+    #[serde(default)]
+    ban: bool,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
 struct Player {
     name: String,
     score: i32,
+    bot: Option<Level>,
+}
+
+// a member without a seat, keeping their score until they sit back down
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct Waiting {
+    id: Uuid,
+    player: Player,
 }
 
-#[derive(PartialEq, Serialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
 enum Status {
     Lobby,
     Bidding,
+    Doubling,
     Playing,
     Finished,
 }
 
-pub struct Lobby {
+// everything needed to rebuild a lobby after a restart
+#[derive(Deserialize, Serialize)]
+pub struct Snapshot {
     status: Status,
     users: HashMap<Uuid, usize>,
     players: Vec<Player>,
+    waiting: Vec<Waiting>,
+    game: Option<SavedGame>,
+    history: Vec<Value>,
+    games: usize,
+    redeals: Vec<Value>,
+    chat: Vec<Msg>,
+    seq: u64,
+    host: Option<Uuid>,
+    banned: HashSet<Uuid>,
+    accounts: HashSet<Uuid>,
+    #[serde(flatten)]
+    settings: Settings,
+}
+
+pub struct Lobby {
+    status: Status,
+    users: HashMap<Uuid, usize>, // seat of each seated user
+    players: Vec<Player>,
+    waiting: Vec<Waiting>, // queue for the next free seat
     game: Game,
+    history: Vec<Value>, // replays of the latest finished games
+    games: usize,        // finished games, including those no longer kept
+    redeals: Vec<Value>, // deals no one bid on in the current game
     chat: Vec<Msg>,
-    tx: broadcast::Sender<ServerMsg>,
+    seq: u64,
+    host: Option<Uuid>,
+    banned: HashSet<Uuid>,   // users who cannot rejoin
+    accounts: HashSet<Uuid>, // members who are not guests, so their games count
+    settings: Settings,
+    online: HashMap<Uuid, usize>, // open connections per user
+    stats: Option<StatsRef>,
+    tx: broadcast::Sender<Update>,
+}
+
+pub fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
 }
+
 impl Lobby {
+    // most chat messages replayed to a reconnecting client
+    const MAX_REPLAY: usize = 250;
+    // most game replays kept, older games are dropped
+    const MAX_HISTORY: usize = 50;
+    const MAX_WAITING: usize = 6;
+    const WAITING_IDX: usize = 10;
+    const MIN_TURN_LIMIT: u64 = 5;
+    const MAX_TURN_LIMIT: u64 = 300;
+    // how long the host can be disconnected before someone else takes over
+    pub const HOST_GRACE: Duration = Duration::from_secs(30);
+
     pub fn new() -> Self {
         let (tx, _) = broadcast::channel(50);
         Self {
             status: Status::Lobby,
             users: HashMap::new(),
             players: Vec::new(),
+            waiting: Vec::new(),
             game: Default::default(),
+            history: Vec::new(),
+            games: 0,
+            redeals: Vec::new(),
             chat: Vec::new(),
+            seq: 0,
+            host: None,
+            banned: HashSet::new(),
+            accounts: HashSet::new(),
+            settings: Settings::default(),
+            online: HashMap::new(),
+            stats: None,
             tx,
         }
     }
 
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            status: self.status,
+            users: self.users.clone(),
+            players: self.players.clone(),
+            waiting: self.waiting.clone(),
+            game: (self.status != Status::Lobby).then(|| self.game.save()),
+            history: self.history.clone(),
+            games: self.games,
+            redeals: self.redeals.clone(),
+            chat: self.chat.clone(),
+            seq: self.seq,
+            host: self.host,
+            banned: self.banned.clone(),
+            accounts: self.accounts.clone(),
+            settings: self.settings.clone(),
+        }
+    }
+
+    pub fn restore(snapshot: Snapshot) -> Result<Self, String> {
+        let mut lobby = Self::new();
+        if let Some(game) = snapshot.game {
+            lobby.game = Game::load(game)?;
+        }
+        lobby.status = snapshot.status;
+        lobby.users = snapshot.users;
+        lobby.players = snapshot.players;
+        lobby.waiting = snapshot.waiting;
+        lobby.history = snapshot.history;
+        lobby.games = snapshot.games;
+        lobby.redeals = snapshot.redeals;
+        lobby.chat = snapshot.chat;
+        lobby.seq = snapshot.seq;
+        lobby.host = snapshot.host;
+        lobby.banned = snapshot.banned;
+        lobby.accounts = snapshot.accounts;
+        lobby.settings = snapshot.settings;
+        // the clock restarts for whoever was on turn
+        lobby.start_turn();
+        Ok(lobby)
+    }
+
+    // where finished games are recorded
+    pub fn set_stats(&mut self, stats: StatsRef) {
+        self.stats = Some(stats);
+    }
+
     pub fn user_idx(&self, user: &User) -> Option<usize> {
         self.users.get(&user.id).copied()
     }
@@ -76,55 +289,456 @@ impl Lobby {
         &self.players[self.game.turn()].name
     }
 
-    pub fn join(&mut self, user: &User) -> Result<(), AppError> {
-        // check whether possible to join
-        if self.users.contains_key(&user.id) {
-            return Err("already joined the lobby".into());
-        }
+    fn in_game(&self) -> bool {
+        matches!(
+            self.status,
+            Status::Bidding | Status::Doubling | Status::Playing
+        )
+    }
+
+    fn check_seat(&self) -> Result<usize, AppError> {
         let idx = self.players.len();
-        if idx >= 4 {
+        if idx >= self.settings.seats {
             return Err("lobby is full".into());
         }
-        if self.status == Status::Bidding || self.status == Status::Playing {
+        if self.in_game() {
             return Err("game in progress".into());
         }
+        Ok(idx)
+    }
 
-        // add the user
-        self.users.insert(user.id, idx);
+    fn seat(&mut self, name: String, bot: Option<Level>) {
+        self.send_msg(9, format!("{} joined the game.", name));
         self.players.push(Player {
-            name: user.username.clone(),
+            name,
             score: 0,
+            bot,
         });
-        self.send_msg(9, format!("{} joined the game.", user.username));
+        self.send_state();
+    }
+
+    fn waiting_pos(&self, id: &Uuid) -> Option<usize> {
+        self.waiting.iter().position(|w| w.id == *id)
+    }
+
+    pub fn join(&mut self, user: &User) -> Result<(), AppError> {
+        // check whether possible to join
+        if self.users.contains_key(&user.id) || self.waiting_pos(&user.id).is_some() {
+            return Err("already joined the lobby".into());
+        }
+        if self.banned.contains(&user.id) {
+            return Err("banned from the lobby".into());
+        }
+
+        // take a free seat or wait for one, the first user hosts
+        match self.check_seat() {
+            Ok(idx) => {
+                self.users.insert(user.id, idx);
+                self.seat(user.username.clone(), None);
+            }
+            Err(_) if self.waiting.len() < Self::MAX_WAITING => {
+                self.waiting.push(Waiting {
+                    id: user.id,
+                    player: Player {
+                        name: user.username.clone(),
+                        score: 0,
+                        bot: None,
+                    },
+                });
+                self.send_msg(9, format!("{} is waiting for a seat.", user.username));
+                self.send_state();
+            }
+            Err(_) => return Err("lobby is full".into()),
+        }
+        if !user.guest {
+            self.accounts.insert(user.id);
+        }
+        self.host.get_or_insert(user.id);
+        Ok(())
+    }
+
+    // seated players can only leave between games, those waiting at any time
+    pub fn leave(&mut self, id: &Uuid) -> Result<(), AppError> {
+        let player = match (self.waiting_pos(id), self.users.get(id)) {
+            (Some(pos), _) => self.waiting.remove(pos).player,
+            (None, Some(&idx)) => {
+                self.check_idle()?;
+                let player = self.remove_seat(idx);
+                self.fill_seats();
+                player
+            }
+            (None, None) => return Err("not in the lobby".into()),
+        };
+
+        self.send_msg(9, format!("{} left the game.", player.name));
+        if self.host == Some(*id) {
+            self.host = self.first_user(true).or_else(|| self.first_user(false));
+            self.announce_host();
+        }
+        self.send_state();
+        Ok(())
+    }
 
+    pub fn kick(&mut self, id: &Uuid, kick: Kick) -> Result<(), AppError> {
+        self.check_host(id)?;
+        if kick.waiting {
+            return self.kick_waiting(id, kick);
+        }
+        self.check_idle()?;
+        if kick.idx >= self.players.len() {
+            return Err("no player in that seat".into());
+        }
+        if self.users.get(id) == Some(&kick.idx) {
+            return Err("cannot kick yourself".into());
+        }
+
+        let user = self
+            .users
+            .iter()
+            .find(|(_, &idx)| idx == kick.idx)
+            .map(|(&id, _)| id);
+        if kick.ban {
+            self.banned.extend(user);
+        }
+        let player = self.remove_seat(kick.idx);
+        self.fill_seats();
+        self.send_msg(
+            9,
+            format!(
+                "{} was {} by the host.",
+                player.name,
+                if kick.ban { "banned" } else { "kicked" }
+            ),
+        );
+        self.send_state();
+        Ok(())
+    }
+
+    // members without a seat can be removed mid-game
+    fn kick_waiting(&mut self, id: &Uuid, kick: Kick) -> Result<(), AppError> {
+        let Some(waiting) = self.waiting.get(kick.idx) else {
+            return Err("no one waiting in that place".into());
+        };
+        if waiting.id == *id {
+            return Err("cannot kick yourself".into());
+        }
+
+        let waiting = self.waiting.remove(kick.idx);
+        if kick.ban {
+            self.banned.insert(waiting.id);
+        }
+        self.send_msg(
+            9,
+            format!(
+                "{} was {} by the host.",
+                waiting.player.name,
+                if kick.ban { "banned" } else { "kicked" }
+            ),
+        );
         self.send_state();
         Ok(())
     }
 
-    pub fn start(&mut self) -> Result<(), AppError> {
-        match self.status {
-            Status::Lobby => {
-                if self.players.len() < 3 {
-                    return Err("not enough players".into());
-                }
+    // free a seat and move everyone after it down one
+    fn remove_seat(&mut self, idx: usize) -> Player {
+        let player = self.players.remove(idx);
+        self.users.retain(|_, i| *i != idx);
+        for i in self.users.values_mut() {
+            if *i > idx {
+                *i -= 1;
+            }
+        }
+        // the last table no longer matches the seats
+        self.status = Status::Lobby;
+        player
+    }
+
+    // seat those waiting in the order they came
+    fn fill_seats(&mut self) {
+        while self.players.len() < self.settings.seats && !self.waiting.is_empty() {
+            let Waiting { id, player } = self.waiting.remove(0);
+            self.send_msg(9, format!("{} sat down.", player.name));
+            self.users.insert(id, self.players.len());
+            self.players.push(player);
+        }
+    }
+
+    // bots and then the losers of the last game give their seats to those waiting
+    // biggest loss first
+    fn rotate(&mut self) {
+        let deltas = self.game.score_deltas();
+        let mut out: Vec<_> = (0..deltas.len())
+            .filter(|&i| self.players[i].bot.is_some())
+            .collect();
+        let mut losers: Vec<_> = (0..deltas.len())
+            .filter(|&i| self.players[i].bot.is_none() && deltas[i] < 0)
+            .collect();
+        losers.sort_by_key(|&i| deltas[i]);
+        out.extend(losers);
+        out.truncate(self.waiting.len());
+
+        for idx in out {
+            let Waiting { id, player } = self.waiting.remove(0);
+            self.send_msg(
+                9,
+                format!(
+                    "{} sits out. {} takes their seat.",
+                    self.players[idx].name, player.name
+                ),
+            );
+            let loser = std::mem::replace(&mut self.players[idx], player);
+            // bots are not kept waiting
+            let out = self
+                .users
+                .iter()
+                .find(|(_, &i)| i == idx)
+                .map(|(&id, _)| id);
+            if let Some(out) = out {
+                self.users.remove(&out);
+                self.waiting.push(Waiting {
+                    id: out,
+                    player: loser,
+                });
+            }
+            self.users.insert(id, idx);
+        }
+    }
+
+    // those waiting get free seats before bots do
+    pub fn add_bot(&mut self, id: &Uuid, level: Level) -> Result<(), AppError> {
+        self.check_host(id)?;
+        self.check_idle()?;
+        self.fill_seats();
+        let idx = self.check_seat()?;
+        self.seat(format!("Bot {}", idx + 1), Some(level));
+        Ok(())
+    }
+
+    // the bot to move and what it can see
+    pub fn bot_view(&self) -> Option<(Level, View)> {
+        if !self.in_game() {
+            return None;
+        }
+        let idx = self.game.turn();
+        let level = self.players[idx].bot?;
+        Some((level, self.game.view(idx)))
+    }
+
+    // None if the bot could not decide
+    // a failed move falls back to the automatic one so the table does not stall
+    pub fn bot_act(&mut self, idx: usize, action: Option<Action>) {
+        if self.bot_view().is_none_or(|(_, view)| view.idx != idx) {
+            return;
+        }
+        let result = match action {
+            Some(action) => self.act(idx, action),
+            None => Err("no move chosen".into()),
+        };
+        if let Err(e) = result {
+            warn!(error = e.0, "bot move failed");
+            if let Err(e) = self.act(idx, self.game.auto_action()) {
+                warn!(error = e.0, "automatic move failed");
+                // wake the driver to try again
+                self.send_state();
             }
-            Status::Bidding | Status::Playing => return Err("game in progress".into()),
-            _ => {}
         }
+    }
+
+    fn act(&mut self, idx: usize, action: Action) -> Result<(), AppError> {
+        match action {
+            Action::Bid(val) => self.bid(idx, val),
+            Action::Double(val) => self.double(idx, val),
+            Action::Play(hand) => self.play(idx, hand.cards().iter().map(Card::value).collect()),
+        }
+    }
+
+    // settings can only change between games
+    fn check_idle(&self) -> Result<(), AppError> {
+        if self.in_game() {
+            return Err("game in progress".into());
+        }
+        Ok(())
+    }
+
+    fn check_host(&self, id: &Uuid) -> Result<(), AppError> {
+        if self.host != Some(*id) {
+            return Err("only the host can do that".into());
+        }
+        Ok(())
+    }
+
+    // rules for the next game
+    fn rules(&self) -> Rules {
+        self.settings
+            .rules
+            .clone()
+            .unwrap_or_else(|| Rules::standard(self.settings.seats))
+    }
 
-        self.game = Game::new(self.players.len());
+    pub fn update_settings(&mut self, id: &Uuid, patch: SettingsPatch) -> Result<(), AppError> {
+        self.check_host(id)?;
+        self.check_idle()?;
+
+        // check everything before changing anything
+        if let Some(seats) = patch.seats {
+            card::check_players(seats).map_err(AppError)?;
+            if seats < self.players.len() {
+                return Err("too many players seated".into());
+            }
+        }
+        if let Some(Some(rules)) = &patch.rules {
+            rules.validate().map_err(AppError)?;
+        }
+        if let Some(Some(secs)) = patch.turn_limit {
+            if !(Self::MIN_TURN_LIMIT..=Self::MAX_TURN_LIMIT).contains(&secs) {
+                return Err(AppError(format!(
+                    "turn limit must be between {} and {} seconds",
+                    Self::MIN_TURN_LIMIT,
+                    Self::MAX_TURN_LIMIT
+                )));
+            }
+        }
+
+        let settings = &mut self.settings;
+        if let Some(seats) = patch.seats {
+            settings.seats = seats;
+        }
+        if let Some(rules) = patch.rules {
+            settings.rules = rules;
+        }
+        if let Some(secs) = patch.turn_limit {
+            settings.turn_limit = secs;
+        }
+        if let Some(private) = patch.private {
+            settings.private = private;
+        }
+        self.send_state();
+        Ok(())
+    }
+
+    pub fn connect(&mut self, id: Uuid) {
+        *self.online.entry(id).or_default() += 1;
+    }
+
+    // returns whether the host is now offline
+    pub fn disconnect(&mut self, id: Uuid) -> bool {
+        if let Some(count) = self.online.get_mut(&id) {
+            *count -= 1;
+            if *count == 0 {
+                self.online.remove(&id);
+            }
+        }
+        self.host
+            .is_some_and(|host| !self.online.contains_key(&host))
+    }
+
+    // the user in the lowest seat or first in line, optionally only among those connected
+    fn first_user(&self, online: bool) -> Option<Uuid> {
+        let online = |id: &Uuid| !online || self.online.contains_key(id);
+        self.users
+            .iter()
+            .filter(|(id, _)| online(id))
+            .min_by_key(|(_, &idx)| idx)
+            .map(|(&id, _)| id)
+            .or_else(|| self.waiting.iter().map(|w| w.id).find(online))
+    }
+
+    fn announce_host(&mut self) {
+        let Some(id) = self.host else {
+            return;
+        };
+        let name = match (self.users.get(&id), self.waiting_pos(&id)) {
+            (Some(&idx), _) => &self.players[idx].name,
+            (None, Some(pos)) => &self.waiting[pos].player.name,
+            (None, None) => return,
+        };
+        self.send_msg(9, format!("{} is now the host.", name));
+    }
+
+    // hand the lobby to the first seated user who is still around
+    pub fn pass_host(&mut self) {
+        if self
+            .host
+            .is_some_and(|host| self.online.contains_key(&host))
+        {
+            return;
+        }
+        let Some(id) = self.first_user(true) else {
+            return;
+        };
+        self.host = Some(id);
+        self.announce_host();
+        self.send_state();
+    }
+
+    // start the clock for the player on turn, bots are not timed
+    fn start_turn(&mut self) {
+        let timed = self.in_game() && self.players[self.game.turn()].bot.is_none();
+        self.game.set_deadline(
+            self.settings
+                .turn_limit
+                .filter(|_| timed)
+                .map(|secs| now_ms() + secs * 1000),
+        );
+    }
+
+    pub fn time_left(&self) -> Option<Duration> {
+        self.game
+            .deadline()
+            .map(|deadline| Duration::from_millis(deadline.saturating_sub(now_ms())))
+    }
+
+    // move for the player on turn if their time is up
+    pub fn expire(&mut self) {
+        if self.time_left().is_none_or(|left| !left.is_zero()) {
+            return;
+        }
+        let idx = self.game.turn();
+        self.send_msg(9, format!("{} ran out of time.", self.players[idx].name));
+        if let Err(e) = self.act(idx, self.game.auto_action()) {
+            warn!(error = e.0, "automatic move failed");
+            self.game.set_deadline(None);
+        }
+    }
+
+    pub fn start(&mut self, id: &Uuid, seed: Option<u64>) -> Result<(), AppError> {
+        self.check_host(id)?;
+        self.check_idle()?;
+        if self.status == Status::Finished {
+            self.rotate();
+        }
+        self.fill_seats();
+        if self.players.len() < self.settings.seats {
+            return Err("not enough players".into());
+        }
+
+        self.game = Game::new(self.settings.seats, seed.unwrap_or_else(Game::random_seed))
+            .map_err(AppError)?
+            .with_rules(self.rules());
+        self.redeals.clear();
         self.status = Status::Bidding;
         self.send_msg(
             9,
             format!("Game started. {} begins the bidding.", self.current_name()),
         );
+        self.start_turn();
         self.send_state();
         Ok(())
     }
 
     pub fn bid(&mut self, idx: usize, val: usize) -> Result<(), AppError> {
+        let action = match (self.game.rules().bid_mode, val, self.game.high_bid()) {
+            (_, 0, _) => "passed".to_string(),
+            (BidMode::Points, _, _) => format!("bid {}", val),
+            (BidMode::Rob, _, 0) => "called the landlord".to_string(),
+            (BidMode::Rob, _, _) => "robbed the landlord".to_string(),
+        };
         if self.game.bid(idx, val).map_err(AppError)? {
-            self.game = Game::new(self.players.len());
+            self.redeals.push(self.game.replay());
+            // keep redeals reproducible
+            self.game = Game::new(self.game.players(), self.game.redeal_seed())
+                .map_err(AppError)?
+                .with_rules(self.rules());
             self.send_msg(
                 9,
                 format!(
@@ -133,19 +747,13 @@ impl Lobby {
                 ),
             );
         } else {
-            self.send_msg(
-                9,
-                format!(
-                    "{} {}.",
-                    self.players[idx].name,
-                    match val {
-                        0 => "passed".to_string(),
-                        _ => format!("bid {}", val),
-                    },
-                ),
-            );
+            self.send_msg(9, format!("{} {}.", self.players[idx].name, action));
             if self.game.playing() {
-                self.status = Status::Playing;
+                self.status = if self.game.doubling() {
+                    Status::Doubling
+                } else {
+                    Status::Playing
+                };
                 self.send_msg(
                     9,
                     format!(
@@ -156,12 +764,45 @@ impl Lobby {
                 );
             }
         }
+        self.start_turn();
+        self.send_state();
+        Ok(())
+    }
+
+    pub fn double(&mut self, idx: usize, val: bool) -> Result<(), AppError> {
+        self.game.double(idx, val).map_err(AppError)?;
+        let action = match (val, idx == self.game.landlord()) {
+            (true, true) => "redoubled",
+            (true, false) => "doubled",
+            (false, _) => "did not double",
+        };
+        self.send_msg(9, format!("{} {}.", self.players[idx].name, action));
+        if !self.game.doubling() {
+            self.status = Status::Playing;
+        }
+        self.start_turn();
+        self.send_state();
+        Ok(())
+    }
+
+    pub fn reveal(&mut self, idx: usize) -> Result<(), AppError> {
+        if !self.in_game() {
+            return Err("no game in progress".into());
+        }
+        self.game.reveal(idx).map_err(AppError)?;
+        self.send_msg(
+            9,
+            format!(
+                "{} revealed their hand! Their stakes double.",
+                self.players[idx].name
+            ),
+        );
         self.send_state();
         Ok(())
     }
 
     pub fn play(&mut self, idx: usize, cards: Vec<usize>) -> Result<(), AppError> {
-        let hand = Hand::new(self.players.len(), cards).map_err(AppError)?;
+        let hand = Hand::new(self.game.rules(), cards).map_err(AppError)?;
         let action = if hand.is_pass() {
             "passed".to_string()
         } else {
@@ -178,9 +819,7 @@ impl Lobby {
 
             let landlord = self.game.landlord();
             let mask = self.game.played_mask();
-            let mut delta = self.game.score_delta() as i32;
-            if mask < 6 {
-                delta *= 2;
+            if self.game.domination() {
                 self.send_msg(
                     9,
                     format!(
@@ -189,42 +828,195 @@ impl Lobby {
                     ),
                 );
             }
-            let mut landlord_delta = delta * (self.players.len() - 1) as i32;
 
-            let msg;
-            if winner == landlord {
-                msg = format!(
+            let deltas = self.game.score_deltas();
+            let landlord_delta = deltas[landlord];
+            let delta = deltas[(landlord + 1) % deltas.len()];
+            let msg = if deltas
+                .iter()
+                .enumerate()
+                .any(|(i, &d)| i != landlord && d != delta)
+            {
+                // peasants who doubled settle for more
+                let peasants: Vec<_> = (0..deltas.len())
+                    .filter(|&i| i != landlord)
+                    .map(|i| format!("{} {:+}", self.players[i].name, deltas[i]))
+                    .collect();
+                format!(
+                    "The landlord {} {:+}. {}.",
+                    if landlord_delta > 0 { "wins" } else { "loses" },
+                    landlord_delta,
+                    peasants.join(", ")
+                )
+            } else if landlord_delta > 0 {
+                format!(
                     "The landlord wins +{}. Peasants lose -{}.",
-                    landlord_delta, delta
-                );
+                    landlord_delta, -delta
+                )
             } else {
-                msg = format!(
+                format!(
                     "Peasants win +{}. The landlord loses -{}.",
-                    delta, landlord_delta
-                );
-                delta *= -1;
-                landlord_delta *= -1;
-            }
+                    delta, -landlord_delta
+                )
+            };
             self.send_msg(9, msg);
 
-            self.players.iter_mut().for_each(|x| x.score -= delta);
-            self.players[landlord].score += landlord_delta + delta;
+            self.record_stats(&deltas);
+            for (player, delta) in self.players.iter_mut().zip(deltas) {
+                player.score += delta;
+            }
+
+            let mut replay = self.game.replay();
+            replay["players"] = self.players.iter().map(|p| p.name.clone()).collect();
+            replay["redeals"] = std::mem::take(&mut self.redeals).into();
+            self.history.push(replay);
+            self.games += 1;
+            if self.history.len() > Self::MAX_HISTORY {
+                self.history.remove(0);
+            }
 
             self.status = Status::Finished;
         }
+        self.start_turn();
         self.send_state();
         Ok(())
     }
 
-    pub fn subscribe(&self) -> broadcast::Receiver<ServerMsg> {
+    // count the finished game for every seated account
+    // ratings only change when every seat is an account, so guests and bots cannot be farmed
+    fn record_stats(&self, deltas: &[i32]) {
+        let Some(stats) = &self.stats else {
+            return;
+        };
+        let mut stats = stats.lock().unwrap();
+        let players = self.game.players();
+        let landlord = self.game.landlord();
+        let mut seats = vec![None; players];
+        for (&id, &idx) in &self.users {
+            if self.accounts.contains(&id) {
+                seats[idx] = Some(id);
+            }
+        }
+        let rated: Option<Vec<_>> = seats.iter().copied().collect();
+        if let Some(rated) = rated {
+            let ratings: Vec<_> = rated
+                .iter()
+                .map(|id| stats.ratings(id).get(players))
+                .collect();
+            let ratings = rating::update(
+                &ratings,
+                landlord,
+                deltas[landlord] > 0,
+                self.game.bid_level(),
+            );
+            for (id, rating) in rated.into_iter().zip(ratings) {
+                stats.set_rating(id, players, rating);
+            }
+        }
+
+        let time = now_ms();
+        for (idx, id) in seats.into_iter().enumerate() {
+            let Some(id) = id else {
+                continue;
+            };
+            let won = deltas[idx] > 0;
+            let [bombs, rockets] = self.game.bombs()[idx];
+            stats.record(
+                id,
+                Record {
+                    time,
+                    players,
+                    landlord: idx == landlord,
+                    won,
+                    bombs,
+                    rockets,
+                    domination: won && self.game.domination(),
+                    score: deltas[idx],
+                },
+            );
+        }
+    }
+
+    // private lobbies are left out
+    pub fn listing(&self, code: String) -> Option<Listing> {
+        if self.settings.private {
+            return None;
+        }
+        Some(Listing {
+            code,
+            players: self.players.iter().map(|p| p.name.clone()).collect(),
+            seats: self.settings.seats,
+            waiting: self.waiting.len(),
+            status: self.status,
+            bid_mode: self.rules().bid_mode,
+            custom_rules: self.settings.rules.is_some(),
+            joinable: !self.in_game() && self.players.len() < self.settings.seats,
+            in_progress: self.in_game(),
+        })
+    }
+
+    // game numbers count from the first game, even once its replay is dropped
+    pub fn replay(&self, n: usize) -> Option<&Value> {
+        let dropped = self.games - self.history.len();
+        self.history.get(n.checked_sub(dropped)?)
+    }
+
+    pub fn hint(&self, idx: usize, n: usize) -> Result<Vec<usize>, AppError> {
+        self.game.hint(idx, n).map_err(AppError)
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Update> {
         self.tx.subscribe()
     }
 
+    fn next_seq(&mut self) -> u64 {
+        self.seq += 1;
+        self.seq
+    }
+
+    // missed chat and a fresh state for a client that last saw update since
+    pub fn catch_up(&self, since: u64, id: &Uuid) -> Vec<Update> {
+        let mut updates = Vec::new();
+        // a client ahead of the lobby saw a lobby that was lost, so only the state is sent
+        if since <= self.seq {
+            let start = self.chat.partition_point(|msg| msg.seq <= since);
+            if self.chat.len() - start <= Self::MAX_REPLAY {
+                updates.extend(self.chat[start..].iter().map(|msg| Update {
+                    seq: msg.seq,
+                    msg: ServerMsg::Chat(msg.clone()),
+                }));
+            }
+        }
+        updates.push(Update {
+            seq: self.seq,
+            msg: ServerMsg::State(self.state_for(id)),
+        });
+        updates
+    }
+
+    // members waiting for a seat chat under their name
+    pub fn chat(&mut self, id: &Uuid, text: String) -> Result<(), AppError> {
+        match (self.users.get(id), self.waiting_pos(id)) {
+            (Some(&idx), _) => {
+                // names are kept with the message since seats change hands
+                let name = self.players[idx].name.clone();
+                self.push_msg(idx, Some(name), text);
+            }
+            (None, Some(pos)) => {
+                let name = self.waiting[pos].player.name.clone();
+                self.push_msg(Self::WAITING_IDX, Some(name), text);
+            }
+            (None, None) => return Err("not in the lobby".into()),
+        }
+        Ok(())
+    }
+
     pub fn send_msg(&mut self, idx: usize, text: String) {
-        let mut time = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_millis() as u64;
+        self.push_msg(idx, None, text);
+    }
+
+    fn push_msg(&mut self, idx: usize, name: Option<String>, text: String) {
+        let mut time = now_ms();
 
         // make timestamp unique
         if let Some(last) = self.chat.last() {
@@ -233,9 +1025,19 @@ impl Lobby {
             }
         }
 
-        let msg = Msg { text, idx, time };
+        let seq = self.next_seq();
+        let msg = Msg {
+            text,
+            idx,
+            time,
+            seq,
+            name,
+        };
         self.chat.push(msg.clone());
-        let _ = self.tx.send(ServerMsg::Chat(msg));
+        let _ = self.tx.send(Update {
+            seq,
+            msg: ServerMsg::Chat(msg),
+        });
     }
 
     pub fn chat_before(&self, time: Option<u64>, limit: usize) -> Vec<Msg> {
@@ -257,13 +1059,40 @@ impl Lobby {
         // status
         lobby.insert(
             "status".to_string(),
-            serde_json::to_value(&self.status).unwrap(),
+            serde_json::to_value(self.status).unwrap(),
         );
-        // player list
+        // player list and those waiting for a seat
         lobby.insert(
             "players".to_string(),
             serde_json::to_value(&self.players).unwrap(),
         );
+        lobby.insert(
+            "waiting".to_string(),
+            self.waiting
+                .iter()
+                .map(|w| serde_json::to_value(&w.player).unwrap())
+                .collect(),
+        );
+        // rules of the current game, or of the next one between games
+        let rules = if self.in_game() {
+            self.game.rules().clone()
+        } else {
+            self.rules()
+        };
+        lobby.insert("rules".to_string(), serde_json::to_value(rules).unwrap());
+        // host seat and options
+        lobby.insert(
+            "host".to_string(),
+            Value::from(self.host.and_then(|id| self.users.get(&id).copied())),
+        );
+        lobby.insert(
+            "settings".to_string(),
+            serde_json::to_value(&self.settings).unwrap(),
+        );
+        // sequence number of the latest update
+        lobby.insert("seq".to_string(), Value::from(self.seq));
+        // number of finished games, the oldest replays may have been dropped
+        lobby.insert("games".to_string(), Value::from(self.games));
         // game state
         if self.status != Status::Lobby {
             lobby.insert("game".to_string(), self.game.serialize());
@@ -271,7 +1100,16 @@ impl Lobby {
         Value::from(lobby)
     }
 
-    pub fn serialize_idx(&self, mut state: Value, idx: usize) -> Value {
+    // add what a user can see to a serialized state, spectators see nothing more
+    pub fn state_with(&self, mut state: Value, id: &Uuid) -> Value {
+        // the host may be waiting rather than seated
+        state["hosting"] = Value::from(self.host == Some(*id));
+        if let Some(pos) = self.waiting_pos(id) {
+            state["queued"] = Value::from(pos);
+        }
+        let Some(&idx) = self.users.get(id) else {
+            return state;
+        };
         state["idx"] = Value::from(idx);
         if self.status != Status::Lobby && idx < self.game.players() {
             state["hand"] = self.game.serialize_cards(idx);
@@ -279,7 +1117,250 @@ impl Lobby {
         state
     }
 
-    pub fn send_state(&self) {
-        let _ = self.tx.send(ServerMsg::State(self.serialize()));
+    // state as seen by a user
+    pub fn state_for(&self, id: &Uuid) -> Value {
+        self.state_with(self.serialize(), id)
+    }
+
+    pub fn send_state(&mut self) {
+        let seq = self.next_seq();
+        let _ = self.tx.send(Update {
+            seq,
+            msg: ServerMsg::State(self.serialize()),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(name: &str) -> User {
+        User {
+            id: Uuid::new_v4(),
+            username: name.to_string(),
+            guest: true,
+        }
+    }
+
+    // a lobby with the given users joined in order
+    fn lobby(names: &[&str]) -> (Lobby, Vec<User>) {
+        let mut lobby = Lobby::new();
+        let users: Vec<_> = names.iter().map(|name| user(name)).collect();
+        for user in &users {
+            lobby.join(user).unwrap();
+        }
+        (lobby, users)
+    }
+
+    fn names(lobby: &Lobby) -> Vec<&str> {
+        lobby.players.iter().map(|p| p.name.as_str()).collect()
+    }
+
+    fn kick(idx: usize, waiting: bool, ban: bool) -> Kick {
+        Kick { idx, waiting, ban }
+    }
+
+    // every seat moves automatically until the game ends
+    fn finish_game(lobby: &mut Lobby) {
+        lobby.bid(lobby.game.turn(), 3).unwrap();
+        while lobby.status != Status::Finished {
+            let idx = lobby.game.turn();
+            lobby.act(idx, lobby.game.auto_action()).unwrap();
+        }
+    }
+
+    #[test]
+    fn leaving_compacts_seats() {
+        let (mut lobby, users) = lobby(&["a", "b", "c"]);
+        lobby.leave(&users[1].id).unwrap();
+        assert_eq!(names(&lobby), ["a", "c"]);
+        assert_eq!(lobby.users.get(&users[0].id), Some(&0));
+        assert_eq!(lobby.users.get(&users[1].id), None);
+        assert_eq!(lobby.users.get(&users[2].id), Some(&1));
+        assert!(lobby.leave(&users[1].id).is_err());
+    }
+
+    #[test]
+    fn host_passes_to_first_user() {
+        let (mut lobby, users) = lobby(&["a", "b", "c"]);
+        assert_eq!(lobby.host, Some(users[0].id));
+
+        // no one is online, so the next seat takes over
+        lobby.leave(&users[0].id).unwrap();
+        assert_eq!(lobby.host, Some(users[1].id));
+
+        // an offline host hands over to the first connected user
+        lobby.connect(users[2].id);
+        assert!(lobby.disconnect(users[1].id));
+        lobby.pass_host();
+        assert_eq!(lobby.host, Some(users[2].id));
+        assert!(!lobby.disconnect(users[1].id));
+        assert!(lobby.disconnect(users[2].id));
+    }
+
+    #[test]
+    fn kick_and_ban() {
+        let (mut lobby, users) = lobby(&["a", "b", "c", "d", "e"]);
+        assert!(lobby.kick(&users[1].id, kick(0, false, false)).is_err());
+        assert!(lobby.kick(&users[0].id, kick(0, false, false)).is_err());
+        assert!(lobby.kick(&users[0].id, kick(3, false, false)).is_err());
+
+        // the first in line takes the free seat
+        lobby.kick(&users[0].id, kick(1, false, true)).unwrap();
+        assert_eq!(names(&lobby), ["a", "c", "d"]);
+        assert!(lobby.banned.contains(&users[1].id));
+        assert!(lobby.join(&users[1]).is_err());
+
+        lobby.kick(&users[0].id, kick(0, true, false)).unwrap();
+        assert!(lobby.waiting.is_empty());
+        assert!(lobby.kick(&users[0].id, kick(0, true, false)).is_err());
+        lobby.join(&users[4]).unwrap();
+        assert_eq!(lobby.waiting_pos(&users[4].id), Some(0));
+    }
+
+    #[test]
+    fn waiting_users_fill_free_seats() {
+        let (mut lobby, users) = lobby(&["a", "b", "c", "d", "e"]);
+        assert_eq!(names(&lobby), ["a", "b", "c"]);
+        assert_eq!(lobby.waiting.len(), 2);
+
+        lobby.leave(&users[1].id).unwrap();
+        assert_eq!(names(&lobby), ["a", "c", "d"]);
+        assert_eq!(lobby.users.get(&users[3].id), Some(&2));
+        assert_eq!(lobby.waiting_pos(&users[4].id), Some(0));
+
+        // no bot takes a seat someone is waiting for
+        lobby.leave(&users[2].id).unwrap();
+        assert!(lobby.add_bot(&users[0].id, Level::Easy).is_err());
+        assert_eq!(names(&lobby), ["a", "d", "e"]);
+    }
+
+    #[test]
+    fn rotate_bots_then_biggest_losers() {
+        let (mut lobby, users) = lobby(&["a", "b"]);
+        lobby.add_bot(&users[0].id, Level::Easy).unwrap();
+        let waiting = [user("c"), user("d")];
+        for user in &waiting {
+            lobby.join(user).unwrap();
+        }
+        lobby.start(&users[0].id, Some(1)).unwrap();
+        finish_game(&mut lobby);
+
+        let deltas = lobby.game.score_deltas();
+        // a human loses with this deal
+        let loser = (0..2)
+            .filter(|&i| deltas[i] < 0)
+            .min_by_key(|&i| deltas[i])
+            .unwrap();
+        lobby.start(&users[0].id, Some(2)).unwrap();
+
+        // the bot leaves for good, then the biggest loser waits
+        assert_eq!(lobby.users.get(&waiting[0].id), Some(&2));
+        assert!(lobby.players.iter().all(|p| p.bot.is_none()));
+        assert_eq!(lobby.users.get(&waiting[1].id), Some(&loser));
+        assert_eq!(lobby.waiting.len(), 1);
+        assert_eq!(lobby.waiting[0].id, users[loser].id);
+        assert_eq!(lobby.waiting[0].player.score, deltas[loser]);
+    }
+
+    #[test]
+    fn settings_are_checked_first() {
+        let (mut lobby, users) = lobby(&["a", "b", "c"]);
+        let host = users[0].id;
+        assert!(lobby
+            .update_settings(&users[1].id, SettingsPatch::seats(4))
+            .is_err());
+        assert!(lobby
+            .update_settings(&host, SettingsPatch::seats(5))
+            .is_err());
+
+        // nothing changes when any part is wrong
+        let patch = SettingsPatch {
+            seats: Some(4),
+            turn_limit: Some(Some(1)),
+            ..Default::default()
+        };
+        assert!(lobby.update_settings(&host, patch).is_err());
+        let patch = SettingsPatch {
+            seats: Some(4),
+            rules: Some(Some(Rules {
+                max_bid: 0,
+                ..Rules::standard(4)
+            })),
+            ..Default::default()
+        };
+        assert!(lobby.update_settings(&host, patch).is_err());
+        assert_eq!(lobby.settings.seats, 3);
+
+        lobby
+            .update_settings(&host, SettingsPatch::seats(4))
+            .unwrap();
+        lobby.join(&user("d")).unwrap();
+        assert!(lobby
+            .update_settings(&host, SettingsPatch::seats(3))
+            .is_err());
+
+        lobby.start(&host, None).unwrap();
+        assert!(lobby
+            .update_settings(&host, SettingsPatch::default())
+            .is_err());
+    }
+
+    #[test]
+    fn catch_up() {
+        let (mut lobby, users) = lobby(&["a"]);
+        let id = users[0].id;
+        let is_state = |update: &Update| matches!(update.msg, ServerMsg::State(_));
+
+        // up to date, so only the state
+        let updates = lobby.catch_up(lobby.seq, &id);
+        assert_eq!(updates.len(), 1);
+        assert!(is_state(&updates[0]));
+        assert_eq!(updates[0].seq, lobby.seq);
+
+        // missed chat comes first, in order
+        let since = lobby.seq;
+        lobby.chat(&id, "1".to_string()).unwrap();
+        lobby.chat(&id, "2".to_string()).unwrap();
+        let updates = lobby.catch_up(since, &id);
+        let seqs: Vec<_> = updates.iter().map(|u| u.seq).collect();
+        assert_eq!(seqs, [since + 1, since + 2, lobby.seq]);
+        assert!(is_state(&updates[2]));
+
+        // too much was missed to replay
+        let since = lobby.seq;
+        for _ in 0..=Lobby::MAX_REPLAY {
+            lobby.chat(&id, "spam".to_string()).unwrap();
+        }
+        let updates = lobby.catch_up(since, &id);
+        assert_eq!(updates.len(), 1);
+        assert!(is_state(&updates[0]));
+
+        // ahead of a lobby that was lost
+        let updates = lobby.catch_up(lobby.seq + 10, &id);
+        assert_eq!(updates.len(), 1);
+        assert_eq!(updates[0].seq, lobby.seq);
+    }
+
+    #[test]
+    fn snapshot_and_restore() {
+        let (mut lobby, users) = lobby(&["a", "b", "c", "d"]);
+        lobby.chat(&users[3].id, "hi".to_string()).unwrap();
+        lobby.start(&users[0].id, Some(3)).unwrap();
+        finish_game(&mut lobby);
+        lobby.start(&users[0].id, Some(4)).unwrap();
+        lobby.bid(lobby.game.turn(), 1).unwrap();
+
+        let saved = serde_json::to_string(&lobby.snapshot()).unwrap();
+        let restored = Lobby::restore(serde_json::from_str(&saved).unwrap()).unwrap();
+        assert_eq!(restored.serialize(), lobby.serialize());
+        assert_eq!(restored.users, lobby.users);
+        assert_eq!(restored.waiting_pos(&lobby.waiting[0].id), Some(0));
+        assert_eq!(restored.host, lobby.host);
+        assert_eq!(restored.seq, lobby.seq);
+        assert_eq!(restored.chat.len(), lobby.chat.len());
+        assert_eq!(restored.game.replay(), lobby.game.replay());
+        assert_eq!(restored.replay(0), lobby.replay(0));
     }
 }
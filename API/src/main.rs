@@ -3,53 +3,80 @@ use std::error::Error;
 use axum::{
     extract::{
         ws::{Message, WebSocket},
-        Query, State, WebSocketUpgrade,
+        Path, Query, State, WebSocketUpgrade,
     },
     http::StatusCode,
     response::{IntoResponse, Response},
-    routing::{get, post},
+    routing::{get, patch, post},
     Form, Json, Router,
 };
 use axum_extra::extract::{
     cookie::{Cookie, SameSite},
     CookieJar,
 };
-use futures_util::StreamExt;
+use futures_util::{stream::SplitSink, StreamExt};
 use serde::Deserialize;
 use serde_json::json;
+use tokio::sync::broadcast::error::RecvError;
 use tower_http::{
     compression::CompressionLayer,
     services::{ServeDir, ServeFile},
 };
-use tracing::info;
+use tracing::{info, warn};
 use uuid::Uuid;
 
+mod account;
 mod app;
-mod card;
-mod game;
+mod bot;
 mod lobby;
-use app::{AppError, AppState, LobbyIdx, LobbyRef, SendApp, User};
-use lobby::{ClientMsg, ServerMsg};
+mod matchmaking;
+mod stats;
+mod store;
+use app::{AppError, AppState, LobbyRef, SendApp, User};
+use doudizhu::{card, game, rating, rules, strategy};
+use lobby::{ClientMsg, Kick, Lobby, ServerMsg, SettingsPatch, Update};
+use stats::Window;
 
 #[tokio::main]
 async fn main() {
     tracing_subscriber::fmt::init();
 
+    // restore saved sessions and lobbies
+    let state = AppState::new();
+    let save_path = store::path();
+    if let Err(e) = store::load(&state, &save_path).await {
+        warn!(error = e.to_string(), "could not load saved state");
+    }
+    tokio::spawn(store::autosave(state.clone(), save_path.clone()));
+    tokio::spawn(matchmaking::run(state.clone()));
+
     // routes specific to a lobby
     let lobby_router = Router::new()
         .route("/", get(lobby_state))
         .route("/join", post(join_lobby))
+        .route("/leave", post(leave_lobby))
+        .route("/kick", post(kick_player))
+        .route("/settings", patch(update_settings))
         .route("/chat", get(chat_before))
+        .route("/games/{n}/replay", get(game_replay))
         .route("/ws", get(ws_handler));
 
     // api routes
     let api_router = Router::new()
         .route("/login", post(login))
+        .route("/register", post(register))
         .route("/logout", post(logout))
         .route("/me", get(current_user))
         .route("/create", post(create_lobby))
+        .route("/lobbies", get(list_lobbies))
+        .route("/stats/{username}", get(user_stats))
+        .route("/leaderboard", get(leaderboard))
+        .route("/ratings", get(ratings))
+        .route("/matchmaking/join", post(join_matchmaking))
+        .route("/matchmaking/leave", post(leave_matchmaking))
+        .route("/matchmaking/ws", get(matchmaking_ws))
         .nest("/lobby/{lobby_code}", lobby_router)
-        .with_state(AppState::new());
+        .with_state(state.clone());
 
     // serve built files
     let router = Router::new()
@@ -63,71 +90,384 @@ async fn main() {
         .layer(CompressionLayer::new());
 
     let listener = tokio::net::TcpListener::bind("0.0.0.0:8000").await.unwrap();
-    axum::serve(listener, router).await.unwrap();
+    tokio::select! {
+        result = axum::serve(listener, router) => result.unwrap(),
+        _ = shutdown_signal() => info!("shutting down"),
+    }
+
+    if let Err(e) = store::save(&state, &save_path).await {
+        warn!(error = e.to_string(), "could not save state");
+    }
+}
+
+async fn shutdown_signal() {
+    let ctrl_c = tokio::signal::ctrl_c();
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("failed to install signal handler")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
 }
 
+// without a password the user plays as a guest
 #[derive(Deserialize)]
 struct LoginForm {
     username: String,
+    password: Option<String>,
 }
 async fn login(
     State(state): State<AppState>,
     cookies: CookieJar,
     Form(form): Form<LoginForm>,
-) -> Result<impl IntoResponse, impl IntoResponse> {
+) -> Result<impl IntoResponse, AppError> {
     // validate username
-    if form.username.trim().is_empty() {
-        return Err((StatusCode::BAD_REQUEST, "username cannot be empty"));
+    let username = form.username.trim();
+    if username.is_empty() {
+        return Err("username cannot be empty".into());
+    }
+
+    let session = Uuid::new_v4();
+    let account = state
+        .accounts
+        .read()
+        .await
+        .find(username)
+        .map(|(id, account)| (id, account.clone()));
+    let user = match (form.password.filter(|p| !p.is_empty()), account) {
+        (Some(password), Some((id, account))) => {
+            let username = account.username.clone();
+            let verified = tokio::task::spawn_blocking(move || account.verify(&password))
+                .await
+                .unwrap_or(false);
+            if !verified {
+                return Err("wrong username or password".into());
+            }
+            User {
+                id,
+                username,
+                guest: false,
+            }
+        }
+        (Some(_), None) => return Err("wrong username or password".into()),
+        (None, Some(_)) => return Err("username is registered, enter its password".into()),
+        (None, None) => User {
+            id: session,
+            username: username.to_string(),
+            guest: true,
+        },
+    };
+
+    info!(username = user.username, guest = user.guest, "login");
+    Ok(start_session(&state, cookies, session, user).await)
+}
+
+#[derive(Deserialize)]
+struct RegisterForm {
+    username: String,
+    password: String,
+}
+async fn register(
+    State(state): State<AppState>,
+    cookies: CookieJar,
+    Form(form): Form<RegisterForm>,
+) -> Result<impl IntoResponse, AppError> {
+    let username = form.username.trim().to_string();
+    if username.is_empty() {
+        return Err("username cannot be empty".into());
     }
+    // fail before the slow hash if possible
+    if state.accounts.read().await.find(&username).is_some() {
+        return Err("username is taken".into());
+    }
+
+    let hash = tokio::task::spawn_blocking(move || account::hash(&form.password))
+        .await
+        .map_err(|e| AppError(e.to_string()))??;
+    let id = state
+        .accounts
+        .write()
+        .await
+        .insert(username.clone(), hash)?;
 
-    info!(username = form.username, "login");
-    let id = Uuid::new_v4();
-    state.users.insert(id, form.username).await;
+    info!(username, "registered");
+    let user = User {
+        id,
+        username,
+        guest: false,
+    };
+    Ok(start_session(&state, cookies, Uuid::new_v4(), user).await)
+}
 
-    // set session cookie
-    let jar = cookies.add(
-        Cookie::build(("session", id.to_string()))
+async fn start_session(
+    state: &AppState,
+    cookies: CookieJar,
+    session: Uuid,
+    user: User,
+) -> CookieJar {
+    state.users.insert(session, user).await;
+    cookies.add(
+        Cookie::build(("session", session.to_string()))
             .http_only(true)
             .secure(true)
             .same_site(SameSite::Strict),
-    );
-    Ok(jar)
+    )
 }
 
-async fn logout(State(state): State<AppState>, user: User, jar: CookieJar) -> impl IntoResponse {
-    state.users.invalidate(&user.id).await;
+async fn logout(State(state): State<AppState>, _: User, jar: CookieJar) -> impl IntoResponse {
+    if let Some(session) = jar
+        .get("session")
+        .and_then(|c| Uuid::parse_str(c.value()).ok())
+    {
+        state.users.invalidate(&session).await;
+    }
     jar.remove(Cookie::from("session"))
 }
 
 async fn current_user(user: User) -> impl IntoResponse {
-    Json(json!({ "username": user.username }))
+    Json(json!({ "username": user.username, "guest": user.guest }))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum LobbyFilter {
+    Joinable,
+    InProgress,
+}
+#[derive(Deserialize)]
+struct LobbiesQuery {
+    filter: Option<LobbyFilter>,
+}
+async fn list_lobbies(
+    State(state): State<AppState>,
+    Query(LobbiesQuery { filter }): Query<LobbiesQuery>,
+) -> impl IntoResponse {
+    let mut listings = state.listings().await;
+    listings.retain(|listing| match filter {
+        Some(LobbyFilter::Joinable) => listing.joinable,
+        Some(LobbyFilter::InProgress) => listing.in_progress,
+        None => true,
+    });
+    listings.truncate(100);
+    Json(listings)
+}
+
+#[derive(Deserialize)]
+struct StatsQuery {
+    #[serde(default)]
+    window: Window,
+    limit: Option<usize>,
+}
+async fn user_stats(
+    State(state): State<AppState>,
+    Path(username): Path<String>,
+    Query(StatsQuery { window, .. }): Query<StatsQuery>,
+) -> Result<impl IntoResponse, Response> {
+    let account = state
+        .accounts
+        .read()
+        .await
+        .find(&username)
+        .map(|(id, account)| (id, account.username.clone()));
+    let (id, username) = account.ok_or(
+        (
+            StatusCode::NOT_FOUND,
+            AppError(format!("user {} not found", username)),
+        )
+            .into_response(),
+    )?;
+    let stats = state.stats.lock().unwrap();
+    Ok(Json(json!({
+        "username": username,
+        "stats": stats.summary(&id, window),
+        "ratings": stats.ratings(&id),
+    })))
+}
+
+async fn leaderboard(
+    State(state): State<AppState>,
+    Query(StatsQuery { window, limit }): Query<StatsQuery>,
+) -> impl IntoResponse {
+    let board = state
+        .stats
+        .lock()
+        .unwrap()
+        .leaderboard(window, limit.unwrap_or(20).min(100));
+    let accounts = state.accounts.read().await;
+    let board: Vec<_> = board
+        .into_iter()
+        .filter_map(|(id, summary)| {
+            let username = &accounts.get(&id)?.username;
+            Some(json!({ "username": username, "stats": summary }))
+        })
+        .collect();
+    Json(board)
+}
+
+#[derive(Deserialize)]
+struct RatingsQuery {
+    players: Option<usize>,
+    limit: Option<usize>,
+}
+async fn ratings(
+    State(state): State<AppState>,
+    Query(RatingsQuery { players, limit }): Query<RatingsQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let players = players.unwrap_or(3);
+    card::check_players(players).map_err(AppError)?;
+    let rankings = state
+        .stats
+        .lock()
+        .unwrap()
+        .rankings(players, limit.unwrap_or(20).min(100));
+    let accounts = state.accounts.read().await;
+    let rankings: Vec<_> = rankings
+        .into_iter()
+        .filter_map(|(id, rating)| {
+            let username = &accounts.get(&id)?.username;
+            Some(json!({ "username": username, "rating": rating }))
+        })
+        .collect();
+    Ok(Json(rankings))
 }
 
+// settings such as the number of seats can be picked up front
 async fn create_lobby(
     State(state): State<AppState>,
     user: User,
+    settings: Option<Json<SettingsPatch>>,
 ) -> Result<impl IntoResponse, AppError> {
-    let (id, lobby) = state.create_lobby().await;
-    info!(id, "lobby created");
+    let mut lobby = Lobby::new();
+    lobby.join(&user)?;
+    if let Some(Json(patch)) = settings {
+        lobby.update_settings(&user.id, patch)?;
+    }
 
-    lobby.write().await.join(&user)?;
+    let (id, _) = state.create_lobby(lobby).await;
+    info!(id, "lobby created");
     Ok(Json(json!({ "lobbyCode": id })))
 }
 
-async fn lobby_state(LobbyIdx(lobby, idx): LobbyIdx) -> impl IntoResponse {
-    let lobby_read = lobby.read().await;
-    let state = lobby_read.serialize();
-    Json(if let Some(idx) = idx {
-        lobby_read.serialize_idx(state, idx)
-    } else {
-        state
+#[derive(Deserialize)]
+struct MatchmakingJoin {
+    seats: Option<usize>,
+}
+// players are grouped by their rating in the mode
+async fn join_matchmaking(
+    State(state): State<AppState>,
+    user: User,
+    body: Option<Json<MatchmakingJoin>>,
+) -> Result<impl IntoResponse, AppError> {
+    let seats = body.and_then(|Json(body)| body.seats).unwrap_or(3);
+    let rating = state.stats.lock().unwrap().ratings(&user.id).get(seats);
+    let id = user.id;
+    state.matchmaking.join(user, seats, rating).await?;
+    Ok(Json(state.matchmaking.position(&id)))
+}
+
+async fn leave_matchmaking(
+    State(state): State<AppState>,
+    user: User,
+) -> Result<impl IntoResponse, AppError> {
+    state.matchmaking.leave(&user.id)
+}
+
+// tells a queued player which lobby they were matched into
+// closing it gives up the place in line
+async fn matchmaking_ws(
+    ws: WebSocketUpgrade,
+    State(state): State<AppState>,
+    user: User,
+) -> Response {
+    ws.on_upgrade(move |socket| async move {
+        let ticket = state.matchmaking.attach(&user.id);
+        let _ = handle_matchmaking(socket, &state, &user, ticket.is_some()).await;
+        if let Some(ticket) = ticket {
+            state.matchmaking.detach(&user.id, ticket);
+        }
     })
 }
 
+async fn handle_matchmaking(
+    socket: WebSocket,
+    state: &AppState,
+    user: &User,
+    queued: bool,
+) -> Result<(), Box<dyn Error>> {
+    let (mut sender, mut receiver) = socket.split();
+    let mut rx = state.matchmaking.subscribe();
+
+    // the match may have been made before the socket opened
+    if let Some(matched) = state.matchmaking.matched(&user.id).await {
+        return sender.send_json(matched.msg()).await;
+    }
+    let position = state.matchmaking.position(&user.id).filter(|_| queued);
+    let Some(position) = position else {
+        return sender
+            .send_result(Err(AppError::from("not in the matchmaking queue")))
+            .await;
+    };
+    sender.send_json(json!({ "Queued": position })).await?;
+
+    loop {
+        tokio::select! {
+            next = receiver.next() => match next {
+                Some(Ok(_)) => continue,
+                _ => break,
+            },
+            matched = rx.recv() => match matched {
+                Ok(matched) if matched.includes(&user.id) => {
+                    return sender.send_json(matched.msg()).await;
+                }
+                Ok(_) => continue,
+                Err(RecvError::Lagged(_)) => {
+                    if let Some(matched) = state.matchmaking.matched(&user.id).await {
+                        return sender.send_json(matched.msg()).await;
+                    }
+                }
+                Err(RecvError::Closed) => break,
+            },
+        }
+    }
+    Ok(())
+}
+
+async fn lobby_state(lobby: LobbyRef, user: User) -> impl IntoResponse {
+    Json(lobby.read().await.state_for(&user.id))
+}
+
 async fn join_lobby(lobby: LobbyRef, user: User) -> Result<impl IntoResponse, AppError> {
     lobby.write().await.join(&user)
 }
 
+async fn leave_lobby(lobby: LobbyRef, user: User) -> Result<impl IntoResponse, AppError> {
+    lobby.write().await.leave(&user.id)
+}
+
+async fn kick_player(
+    lobby: LobbyRef,
+    user: User,
+    Json(kick): Json<Kick>,
+) -> Result<impl IntoResponse, AppError> {
+    lobby.write().await.kick(&user.id, kick)
+}
+
+async fn update_settings(
+    lobby: LobbyRef,
+    user: User,
+    Json(patch): Json<SettingsPatch>,
+) -> Result<impl IntoResponse, AppError> {
+    lobby.write().await.update_settings(&user.id, patch)
+}
+
 #[derive(Deserialize)]
 struct ChatBeforeQuery {
     before: Option<u64>,
@@ -141,65 +481,151 @@ async fn chat_before(
     Json(lobby.chat_before(before, limit.unwrap_or(50).min(250)))
 }
 
-async fn ws_handler(ws: WebSocketUpgrade, lobby_idx: LobbyIdx) -> Response {
-    ws.on_upgrade(move |socket| async {
-        let _ = handle_socket(socket, lobby_idx).await;
+async fn game_replay(
+    lobby: LobbyRef,
+    Path((_, n)): Path<(String, usize)>,
+) -> Result<impl IntoResponse, Response> {
+    let lobby = lobby.read().await;
+    let replay = lobby.replay(n).ok_or(
+        (
+            StatusCode::NOT_FOUND,
+            AppError(format!("game {} not found", n)),
+        )
+            .into_response(),
+    )?;
+    Ok(Json(replay.clone()))
+}
+
+#[derive(Deserialize)]
+struct WsQuery {
+    seq: Option<u64>, // last update seen before reconnecting
+}
+async fn ws_handler(
+    ws: WebSocketUpgrade,
+    lobby: LobbyRef,
+    user: User,
+    Query(WsQuery { seq }): Query<WsQuery>,
+) -> Response {
+    ws.on_upgrade(move |socket| async move {
+        lobby.write().await.connect(user.id);
+        let _ = handle_socket(socket, &lobby, &user, seq).await;
+
+        // give the host a chance to reconnect before passing the lobby on
+        if lobby.write().await.disconnect(user.id) {
+            tokio::spawn(async move {
+                tokio::time::sleep(Lobby::HOST_GRACE).await;
+                lobby.write().await.pass_host();
+            });
+        }
     })
 }
 
+// seats move when players leave, so the user's seat is looked up on every message
 async fn handle_socket(
     socket: WebSocket,
-    LobbyIdx(lobby, idx): LobbyIdx,
+    lobby: &LobbyRef,
+    user: &User,
+    since: Option<u64>,
 ) -> Result<(), Box<dyn Error>> {
     let (mut sender, mut receiver) = socket.split();
     let mut rx = lobby.read().await.subscribe();
 
-    // check if user is spectator
-    if idx.is_none() {
-        while let Ok(msg) = rx.recv().await {
-            sender.send_json(msg).await?;
-        }
+    // bring a reconnecting client up to date
+    // updates already sent are skipped when they arrive from the channel
+    let mut last_seq = 0;
+    if let Some(since) = since {
+        last_seq = catch_up(&mut sender, lobby, since, user).await?;
     }
-    let idx = idx.unwrap();
 
+    // number of hints given since the last state change
+    let mut hints = 0;
     loop {
         tokio::select! {
             // handle client
             next = receiver.next() => {
-                match next {
-                    Some(Ok(Message::Text(text))) => match serde_json::from_str(text.as_str()) {
-                        Ok(ClientMsg::Chat(msg)) => {
-                            lobby.write().await.send_msg(idx, msg);
-                        }
-                        Ok(ClientMsg::Start) => {
-                            sender.send_result(lobby.write().await.start()).await?;
-                        }
-                        Ok(ClientMsg::Bid(val)) => {
-                            sender.send_result(lobby.write().await.bid(idx, val)).await?;
-                        }
-                        Ok(ClientMsg::Play(hand)) => {
-                            sender.send_result(lobby.write().await.play(idx, hand)).await?;
-                        }
-                        Err(e) => {
-                            sender.send_result(Err(e)).await?;
+                let text = match next {
+                    Some(Ok(Message::Text(text))) => text,
+                    Some(Ok(_)) => continue,
+                    _ => break,
+                };
+                let msg = match serde_json::from_str(text.as_str()) {
+                    Ok(msg) => msg,
+                    Err(e) => {
+                        sender.send_result(Err(e)).await?;
+                        continue;
+                    }
+                };
+
+                // host, membership and chat actions do not need a seat
+                let mut lobby = lobby.write().await;
+                let result = match (msg, lobby.user_idx(user)) {
+                    (ClientMsg::Start(seed), _) => lobby.start(&user.id, seed),
+                    (ClientMsg::Settings(patch), _) => lobby.update_settings(&user.id, patch),
+                    (ClientMsg::Leave, _) => lobby.leave(&user.id),
+                    (ClientMsg::Chat(msg), _) => lobby.chat(&user.id, msg),
+                    (ClientMsg::Kick(kick), _) => lobby.kick(&user.id, kick),
+                    (ClientMsg::AddBot(level), _) => lobby.add_bot(&user.id, level.unwrap_or_default()),
+                    (_, None) => Err(AppError::from("not seated in the lobby")),
+                    (ClientMsg::Bid(val), Some(idx)) => lobby.bid(idx, val),
+                    (ClientMsg::Play(hand), Some(idx)) => lobby.play(idx, hand),
+                    (ClientMsg::Double(val), Some(idx)) => lobby.double(idx, val),
+                    (ClientMsg::Reveal, Some(idx)) => lobby.reveal(idx),
+                    (ClientMsg::Hint, Some(idx)) => match lobby.hint(idx, hints) {
+                        Ok(cards) => {
+                            drop(lobby);
+                            hints += 1;
+                            sender.send_json(json!({ "Hint": cards })).await?;
+                            continue;
                         }
+                        Err(e) => Err(e),
                     },
-                    _ => break,
-                }
+                };
+                drop(lobby);
+                sender.send_result(result).await?;
             }
             // handle server
-            Ok(msg) = rx.recv() => {
-                sender
-                    .send_json(match msg {
-                        ServerMsg::Chat(_) => msg,
-                        ServerMsg::State(state) => {
-                            ServerMsg::State(lobby.read().await.serialize_idx(state, idx))
+            update = rx.recv() => {
+                match update {
+                    Ok(Update { seq, msg }) => {
+                        if seq <= last_seq {
+                            continue;
                         }
-                    })
-                    .await?;
+                        last_seq = seq;
+                        let msg = match msg {
+                            ServerMsg::Chat(_) => msg,
+                            ServerMsg::State(state) => {
+                                hints = 0;
+                                ServerMsg::State(lobby.read().await.state_with(state, &user.id))
+                            }
+                        };
+                        sender.send_json(Update { seq, msg }).await?;
+                    }
+                    // updates were dropped, so the hand may be stale
+                    Err(RecvError::Lagged(_)) => {
+                        hints = 0;
+                        last_seq = catch_up(&mut sender, lobby, last_seq, user).await?;
+                    }
+                    Err(RecvError::Closed) => break,
+                }
             }
-            else => break,
         }
     }
     Ok(())
 }
+
+// send the chat missed since update since and a full state
+// returns the sequence number the client is now at
+async fn catch_up(
+    sender: &mut SplitSink<WebSocket, Message>,
+    lobby: &LobbyRef,
+    since: u64,
+    user: &User,
+) -> Result<u64, Box<dyn Error>> {
+    let updates = lobby.read().await.catch_up(since, &user.id);
+    let mut seq = since;
+    for update in updates {
+        seq = update.seq;
+        sender.send_json(update).await?;
+    }
+    Ok(seq)
+}
@@ -0,0 +1,356 @@
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+use moka::future::Cache;
+use serde::Serialize;
+use serde_json::{json, Value};
+use tokio::{
+    sync::broadcast,
+    time::{interval, sleep, Instant},
+};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::app::{AppError, AppState, User};
+use crate::card;
+use crate::lobby::{Lobby, SettingsPatch};
+use crate::strategy::Level;
+
+// how often waiting players are matched up
+const TICK: Duration = Duration::from_secs(1);
+// how long the longest waiting player waits before bots take the empty seats
+const BACKFILL: Duration = Duration::from_secs(60);
+// how long a player can be queued without a websocket before losing their place
+const GRACE: Duration = Duration::from_secs(15);
+// rating gap allowed between players, widening the longer they wait
+const SPREAD: f64 = 100.0;
+const SPREAD_PER_SEC: f64 = 10.0;
+
+struct Ticket {
+    id: u64, // new on every join, so a stale socket cannot remove a later ticket
+    user: User,
+    seats: usize,
+    rating: f64,
+    joined: Instant,
+    connected: bool,
+    updated: Instant, // last join, for tickets without a socket
+}
+
+// where a group of queued players ended up
+#[derive(Clone)]
+pub struct Matched {
+    users: Vec<Uuid>,
+    lobby: Result<String, String>, // lobby code or why none was made
+}
+impl Matched {
+    pub fn includes(&self, id: &Uuid) -> bool {
+        self.users.contains(id)
+    }
+
+    pub fn msg(&self) -> Value {
+        match &self.lobby {
+            Ok(code) => json!({ "Matched": { "lobbyCode": code } }),
+            Err(e) => json!({ "Error": e }),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct Position {
+    seats: usize,
+    waiting: usize, // players waiting for the same mode, including this one
+}
+
+// queues of players looking for a game, one per number of seats
+pub struct Matchmaking {
+    queue: Mutex<Vec<Ticket>>, // oldest first
+    next_ticket: AtomicU64,
+    matched: Cache<Uuid, Matched>,
+    tx: broadcast::Sender<Matched>,
+}
+impl Matchmaking {
+    pub fn new() -> Self {
+        let (tx, _) = broadcast::channel(50);
+        Self {
+            queue: Mutex::new(Vec::new()),
+            next_ticket: AtomicU64::new(0),
+            // kept around for players who open their websocket after the match
+            matched: Cache::builder()
+                .time_to_live(Duration::from_secs(5 * 60))
+                .build(),
+            tx,
+        }
+    }
+
+    // joining again switches the mode and keeps the place in line
+    pub async fn join(&self, user: User, seats: usize, rating: f64) -> Result<(), AppError> {
+        card::check_players(seats).map_err(AppError)?;
+        self.matched.invalidate(&user.id).await;
+        let id = self.next_ticket.fetch_add(1, Ordering::Relaxed);
+        let now = Instant::now();
+        let mut queue = self.queue.lock().unwrap();
+        if let Some(ticket) = queue.iter_mut().find(|t| t.user.id == user.id) {
+            ticket.id = id;
+            ticket.seats = seats;
+            ticket.rating = rating;
+            ticket.connected = false;
+            ticket.updated = now;
+        } else {
+            queue.push(Ticket {
+                id,
+                user,
+                seats,
+                rating,
+                joined: now,
+                connected: false,
+                updated: now,
+            });
+        }
+        Ok(())
+    }
+
+    pub fn leave(&self, id: &Uuid) -> Result<(), AppError> {
+        let mut queue = self.queue.lock().unwrap();
+        let len = queue.len();
+        queue.retain(|t| t.user.id != *id);
+        if queue.len() == len {
+            return Err("not in the matchmaking queue".into());
+        }
+        Ok(())
+    }
+
+    // ties a websocket to the user's current ticket
+    pub fn attach(&self, id: &Uuid) -> Option<u64> {
+        let mut queue = self.queue.lock().unwrap();
+        let ticket = queue.iter_mut().find(|t| t.user.id == *id)?;
+        ticket.connected = true;
+        Some(ticket.id)
+    }
+
+    // a closed websocket gives up its ticket, unless the user has joined again since
+    pub fn detach(&self, id: &Uuid, ticket: u64) {
+        self.queue
+            .lock()
+            .unwrap()
+            .retain(|t| t.user.id != *id || t.id != ticket);
+    }
+
+    pub fn position(&self, id: &Uuid) -> Option<Position> {
+        let queue = self.queue.lock().unwrap();
+        let seats = queue.iter().find(|t| t.user.id == *id)?.seats;
+        let waiting = queue.iter().filter(|t| t.seats == seats).count();
+        Some(Position { seats, waiting })
+    }
+
+    pub async fn matched(&self, id: &Uuid) -> Option<Matched> {
+        self.matched.get(id).await
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Matched> {
+        self.tx.subscribe()
+    }
+
+    // drop players who never opened a websocket
+    fn expire(&self, now: Instant) {
+        self.queue
+            .lock()
+            .unwrap()
+            .retain(|t| t.connected || now - t.updated < GRACE);
+    }
+
+    // removes the first group that can play together
+    // each player in line, oldest first, looks for the closest ratings within their spread
+    // once they have waited long enough they take anyone, and bots fill the rest
+    fn take_group(&self, now: Instant) -> Option<(usize, Vec<User>)> {
+        let mut queue = self.queue.lock().unwrap();
+        for (i, ticket) in queue.iter().enumerate() {
+            let waited = now - ticket.joined;
+            let backfill = waited >= BACKFILL;
+            let spread = SPREAD + SPREAD_PER_SEC * waited.as_secs_f64();
+
+            let mut others: Vec<_> = (0..queue.len())
+                .filter(|&j| {
+                    let other = &queue[j];
+                    j != i
+                        && other.seats == ticket.seats
+                        && (backfill || (other.rating - ticket.rating).abs() <= spread)
+                })
+                .collect();
+            if !backfill && others.len() + 1 < ticket.seats {
+                continue;
+            }
+            others.sort_by(|&a, &b| {
+                let gap = |j: usize| (queue[j].rating - ticket.rating).abs();
+                gap(a).total_cmp(&gap(b))
+            });
+            others.truncate(ticket.seats - 1);
+
+            let seats = ticket.seats;
+            let mut group = others;
+            group.push(i);
+            group.sort_unstable_by(|a, b| b.cmp(a));
+            let mut users: Vec<_> = group.into_iter().map(|j| queue.remove(j).user).collect();
+            users.reverse();
+            return Some((seats, users));
+        }
+        None
+    }
+
+    // the group is told where to go, or why there is nowhere to go
+    async fn create(&self, state: &AppState, seats: usize, users: Vec<User>) {
+        let lobby = match seat(seats, &users) {
+            Ok(lobby) => {
+                let (code, lobby) = state.create_lobby(lobby).await;
+                info!(code, players = users.len(), seats, "lobby matched");
+                // the host may never show up
+                tokio::spawn(async move {
+                    sleep(Lobby::HOST_GRACE).await;
+                    lobby.write().await.pass_host();
+                });
+                Ok(code)
+            }
+            Err(e) => {
+                warn!(error = e.0, "could not create matched lobby");
+                Err(format!("could not create a lobby: {}", e))
+            }
+        };
+
+        let matched = Matched {
+            users: users.iter().map(|user| user.id).collect(),
+            lobby,
+        };
+        for user in &users {
+            self.matched.insert(user.id, matched.clone()).await;
+        }
+        let _ = self.tx.send(matched);
+    }
+}
+
+// matches players as long as the server is running
+pub async fn run(state: AppState) {
+    let mut interval = interval(TICK);
+    loop {
+        interval.tick().await;
+        state.matchmaking.expire(Instant::now());
+        while let Some((seats, users)) = state.matchmaking.take_group(Instant::now()) {
+            state.matchmaking.create(&state, seats, users).await;
+        }
+    }
+}
+
+// the longest waiting player hosts
+fn seat(seats: usize, users: &[User]) -> Result<Lobby, AppError> {
+    let mut lobby = Lobby::new();
+    lobby.join(&users[0])?;
+    lobby.update_settings(&users[0].id, SettingsPatch::seats(seats))?;
+    for user in &users[1..] {
+        lobby.join(user)?;
+    }
+    for _ in users.len()..seats {
+        lobby.add_bot(&users[0].id, Level::default())?;
+    }
+    Ok(lobby)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // queues a user who joined at the given time
+    fn queue(matchmaking: &Matchmaking, seats: usize, rating: f64, joined: Instant) -> Uuid {
+        let user = User {
+            id: Uuid::new_v4(),
+            username: format!("{}", rating),
+            guest: true,
+        };
+        let id = user.id;
+        matchmaking.queue.lock().unwrap().push(Ticket {
+            id: matchmaking.next_ticket.fetch_add(1, Ordering::Relaxed),
+            user,
+            seats,
+            rating,
+            joined,
+            connected: false,
+            updated: joined,
+        });
+        id
+    }
+
+    fn ids(group: Option<(usize, Vec<User>)>) -> Option<(usize, Vec<Uuid>)> {
+        group.map(|(seats, users)| (seats, users.iter().map(|u| u.id).collect()))
+    }
+
+    #[test]
+    fn spread_widens_with_time() {
+        let matchmaking = Matchmaking::new();
+        let now = Instant::now();
+        let a = queue(&matchmaking, 3, 1500.0, now);
+        let b = queue(&matchmaking, 3, 1550.0, now);
+        let c = queue(&matchmaking, 3, 1950.0, now);
+        assert!(matchmaking.take_group(now).is_none());
+        // the middle rating reaches both others first
+        assert!(matchmaking
+            .take_group(now + Duration::from_secs(29))
+            .is_none());
+        assert_eq!(
+            ids(matchmaking.take_group(now + Duration::from_secs(30))),
+            Some((3, vec![a, b, c]))
+        );
+        assert!(matchmaking.queue.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn closest_ratings_are_matched() {
+        let matchmaking = Matchmaking::new();
+        let now = Instant::now();
+        let a = queue(&matchmaking, 3, 1500.0, now);
+        let b = queue(&matchmaking, 3, 1590.0, now);
+        // another mode never joins the group
+        let c = queue(&matchmaking, 4, 1500.0, now);
+        let d = queue(&matchmaking, 3, 1560.0, now);
+        let e = queue(&matchmaking, 3, 1450.0, now);
+        assert_eq!(ids(matchmaking.take_group(now)), Some((3, vec![a, d, e])));
+        assert_eq!(matchmaking.position(&b).unwrap().waiting, 1);
+        assert_eq!(matchmaking.position(&c).unwrap().seats, 4);
+    }
+
+    #[test]
+    fn bots_backfill_after_waiting() {
+        let matchmaking = Matchmaking::new();
+        let now = Instant::now();
+        let a = queue(&matchmaking, 4, 1500.0, now);
+        let b = queue(&matchmaking, 4, 2500.0, now + Duration::from_secs(10));
+        assert!(matchmaking.take_group(now + BACKFILL - TICK).is_none());
+        // anyone in the same mode is taken along
+        assert_eq!(
+            ids(matchmaking.take_group(now + BACKFILL)),
+            Some((4, vec![a, b]))
+        );
+    }
+
+    #[test]
+    fn tickets_without_socket_expire() {
+        let matchmaking = Matchmaking::new();
+        let now = Instant::now();
+        let a = queue(&matchmaking, 3, 1500.0, now);
+        let b = queue(&matchmaking, 3, 1500.0, now);
+        let ticket = matchmaking.attach(&b).unwrap();
+
+        matchmaking.expire(now + GRACE - TICK);
+        assert!(matchmaking.position(&a).is_some());
+        matchmaking.expire(now + GRACE);
+        assert!(matchmaking.position(&a).is_none());
+        assert!(matchmaking.position(&b).is_some());
+
+        // only the current ticket is given up
+        matchmaking.detach(&b, ticket + 1);
+        assert!(matchmaking.position(&b).is_some());
+        matchmaking.detach(&b, ticket);
+        assert!(matchmaking.position(&b).is_none());
+    }
+}
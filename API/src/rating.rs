@@ -0,0 +1,86 @@
+use serde::{Deserialize, Serialize};
+
+pub const INITIAL: f64 = 1500.0;
+// most a bid of 1 can move the landlord's rating
+const K: f64 = 32.0;
+
+// a rating for three players and one for four, since the double deck plays differently
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Ratings {
+    pub three: f64,
+    pub four: f64,
+}
+impl Default for Ratings {
+    fn default() -> Self {
+        Self {
+            three: INITIAL,
+            four: INITIAL,
+        }
+    }
+}
+impl Ratings {
+    pub fn get(&self, players: usize) -> f64 {
+        if players == 3 {
+            self.three
+        } else {
+            self.four
+        }
+    }
+
+    pub fn set(&mut self, players: usize, rating: f64) {
+        if players == 3 {
+            self.three = rating;
+        } else {
+            self.four = rating;
+        }
+    }
+}
+
+// elo for one landlord against a team of peasants rated at their average
+// the landlord takes the whole swing and the peasants split it, so the total is unchanged
+// higher bids, or more robs in rob mode, put more at stake
+pub fn update(ratings: &[f64], landlord: usize, landlord_won: bool, bid: usize) -> Vec<f64> {
+    let peasants = ratings.len() - 1;
+    let team = (ratings.iter().sum::<f64>() - ratings[landlord]) / peasants as f64;
+    let expected = 1.0 / (1.0 + 10f64.powf((team - ratings[landlord]) / 400.0));
+    let score = if landlord_won { 1.0 } else { 0.0 };
+    let stake = (1 + bid.clamp(1, 5)) as f64 / 2.0;
+    let swing = K * stake * (score - expected);
+
+    ratings
+        .iter()
+        .enumerate()
+        .map(|(i, rating)| {
+            if i == landlord {
+                rating + swing
+            } else {
+                rating - swing / peasants as f64
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn landlord_against_peasants() {
+        // even ratings move by half of k for a bid of 1
+        let ratings = update(&[INITIAL; 3], 0, true, 1);
+        assert_eq!(ratings, vec![INITIAL + 16.0, INITIAL - 8.0, INITIAL - 8.0]);
+        assert_eq!(ratings.iter().sum::<f64>(), 3.0 * INITIAL);
+
+        // beating a stronger team gains more, and a higher bid doubles it
+        let strong = update(&[1500.0, 1700.0, 1700.0, 1700.0], 0, true, 1);
+        assert!(strong[0] - 1500.0 > 16.0);
+        assert!((strong[1] - 1700.0 - (1500.0 - strong[0]) / 3.0).abs() < 1e-9);
+        let high = update(&[1500.0, 1700.0, 1700.0, 1700.0], 0, true, 3);
+        assert!((high[0] - 1500.0 - 2.0 * (strong[0] - 1500.0)).abs() < 1e-9);
+
+        // losing as the favourite costs more than losing as the underdog
+        let favourite = update(&[1700.0, 1500.0, 1500.0], 0, false, 1);
+        let underdog = update(&[1500.0, 1700.0, 1700.0], 0, false, 1);
+        assert!(1700.0 - favourite[0] > 1500.0 - underdog[0]);
+    }
+}
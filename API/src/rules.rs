@@ -0,0 +1,71 @@
+use serde::{Deserialize, Serialize};
+
+// how the landlord is chosen
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub enum BidMode {
+    // bid up to the maximum bid, highest bid wins
+    #[default]
+    Points,
+    // call the landlord then others may rob it, doubling the stakes each time
+    // the caller gets the last word, bids are 1 to call or rob and 0 to pass
+    Rob,
+}
+
+// house rules for a lobby
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Rules {
+    // hands allowed on top of singles, pairs, triples, chains, bombs and rockets
+    pub triple_single: bool,
+    pub triple_pair: bool,
+    pub quad_single: bool,
+    pub quad_pair: bool,
+    pub single_rocket: bool,     // one of each joker is a rocket
+    pub high_kickers: bool,      // 2s and jokers can be kickers
+    pub quad_kicker_bomb: bool,  // quads with kickers beat other hands like a bomb
+    pub double_bomb_size: usize, // smallest bomb that doubles the stakes
+    pub min_chain: [usize; 3],   // shortest chain of singles, pairs and triples
+    pub max_bid: usize,
+    pub bid_mode: BidMode,
+    pub doubling: bool, // peasants may double after bidding, then the landlord may redouble
+    pub reveal: bool,   // players may reveal their hand to double their stakes
+}
+impl Default for Rules {
+    fn default() -> Self {
+        Self::standard(3)
+    }
+}
+impl Rules {
+    // the usual rules, four players use a double deck
+    pub fn standard(players: usize) -> Self {
+        let three = players == 3;
+        Self {
+            triple_single: three,
+            triple_pair: true,
+            quad_single: three,
+            quad_pair: three,
+            single_rocket: three,
+            high_kickers: true,
+            quad_kicker_bomb: false,
+            double_bomb_size: if three { 4 } else { 6 },
+            min_chain: [5, 3, 2],
+            max_bid: 3,
+            bid_mode: BidMode::Points,
+            doubling: false,
+            reveal: false,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if !(1..=5).contains(&self.max_bid) {
+            return Err("maximum bid must be between 1 and 5".to_string());
+        }
+        // a bomb larger than 8 cards never happens, so nothing doubles
+        if !(4..=9).contains(&self.double_bomb_size) {
+            return Err("bomb doubling size must be between 4 and 9".to_string());
+        }
+        if self.min_chain.iter().any(|len| !(2..=12).contains(len)) {
+            return Err("chains must be between 2 and 12 long".to_string());
+        }
+        Ok(())
+    }
+}
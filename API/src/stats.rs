@@ -0,0 +1,224 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::lobby::now_ms;
+use crate::rating::Ratings;
+
+pub type StatsRef = Arc<Mutex<Stats>>;
+
+// a finished game as one account saw it
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Record {
+    pub time: u64,
+    pub players: usize,
+    pub landlord: bool,
+    pub won: bool,
+    pub bombs: usize,
+    pub rockets: usize,
+    pub domination: bool, // won with a domination
+    pub score: i32,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Window {
+    Week,
+    Month,
+    #[default]
+    All,
+}
+impl Window {
+    // earliest game counted
+    fn since(self) -> u64 {
+        const DAY: u64 = 24 * 60 * 60 * 1000;
+        match self {
+            Window::Week => now_ms().saturating_sub(7 * DAY),
+            Window::Month => now_ms().saturating_sub(30 * DAY),
+            Window::All => 0,
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct Summary {
+    games: usize,
+    landlord_games: usize,
+    landlord_win_rate: f64,
+    peasant_games: usize,
+    peasant_win_rate: f64,
+    bombs: usize,
+    rockets: usize,
+    dominations: usize,
+    score: i32,
+}
+impl Summary {
+    fn new<'a>(records: impl Iterator<Item = &'a Record>) -> Self {
+        let mut summary = Self::default();
+        let (mut landlord_wins, mut peasant_wins) = (0, 0);
+        for record in records {
+            summary.games += 1;
+            if record.landlord {
+                summary.landlord_games += 1;
+                landlord_wins += record.won as usize;
+            } else {
+                summary.peasant_games += 1;
+                peasant_wins += record.won as usize;
+            }
+            summary.bombs += record.bombs;
+            summary.rockets += record.rockets;
+            summary.dominations += record.domination as usize;
+            summary.score += record.score;
+        }
+        let rate = |wins, games| {
+            if games == 0 {
+                0.0
+            } else {
+                wins as f64 / games as f64
+            }
+        };
+        summary.landlord_win_rate = rate(landlord_wins, summary.landlord_games);
+        summary.peasant_win_rate = rate(peasant_wins, summary.peasant_games);
+        summary
+    }
+}
+
+// game records of registered accounts, oldest first, and their current ratings
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct Stats {
+    records: HashMap<Uuid, Vec<Record>>,
+    ratings: HashMap<Uuid, Ratings>,
+}
+impl Stats {
+    pub fn ratings(&self, id: &Uuid) -> Ratings {
+        self.ratings.get(id).copied().unwrap_or_default()
+    }
+
+    pub fn set_rating(&mut self, id: Uuid, players: usize, rating: f64) {
+        self.ratings.entry(id).or_default().set(players, rating);
+    }
+
+    // highest rated accounts in a mode, only counting those who have played it
+    pub fn rankings(&self, players: usize, limit: usize) -> Vec<(Uuid, f64)> {
+        let mut rankings: Vec<_> = self
+            .ratings
+            .iter()
+            .filter(|(id, _)| {
+                self.records
+                    .get(id)
+                    .is_some_and(|records| records.iter().any(|r| r.players == players))
+            })
+            .map(|(id, ratings)| (*id, ratings.get(players)))
+            .collect();
+        rankings.sort_by(|a, b| b.1.total_cmp(&a.1));
+        rankings.truncate(limit);
+        rankings
+    }
+
+    pub fn record(&mut self, id: Uuid, record: Record) {
+        self.records.entry(id).or_default().push(record);
+    }
+
+    fn window(&self, id: &Uuid, window: Window) -> &[Record] {
+        let records = self.records.get(id).map_or(&[][..], Vec::as_slice);
+        let since = window.since();
+        &records[records.partition_point(|r| r.time < since)..]
+    }
+
+    pub fn summary(&self, id: &Uuid, window: Window) -> Summary {
+        Summary::new(self.window(id, window).iter())
+    }
+
+    // highest total score first, accounts without games in the window are left out
+    pub fn leaderboard(&self, window: Window, limit: usize) -> Vec<(Uuid, Summary)> {
+        let mut board: Vec<_> = self
+            .records
+            .keys()
+            .map(|id| (*id, self.summary(id, window)))
+            .filter(|(_, summary)| summary.games > 0)
+            .collect();
+        board.sort_by_key(|(_, summary)| (-summary.score, summary.games));
+        board.truncate(limit);
+        board
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: u64 = 24 * 60 * 60 * 1000;
+
+    fn record(days_ago: u64, players: usize, landlord: bool, score: i32) -> Record {
+        Record {
+            time: now_ms() - days_ago * DAY,
+            players,
+            landlord,
+            won: score > 0,
+            bombs: 0,
+            rockets: 0,
+            domination: false,
+            score,
+        }
+    }
+
+    #[test]
+    fn windows() {
+        let id = Uuid::new_v4();
+        let mut stats = Stats::default();
+        for days_ago in [60, 20, 1] {
+            stats.record(id, record(days_ago, 3, true, 2));
+        }
+        stats.record(id, record(0, 3, false, -1));
+
+        let week = stats.summary(&id, Window::Week);
+        assert_eq!((week.games, week.score), (2, 1));
+        assert_eq!((week.landlord_games, week.peasant_games), (1, 1));
+        assert_eq!(week.landlord_win_rate, 1.0);
+        assert_eq!(week.peasant_win_rate, 0.0);
+        assert_eq!(stats.summary(&id, Window::Month).games, 3);
+        assert_eq!(stats.summary(&id, Window::All).games, 4);
+        assert_eq!(stats.summary(&Uuid::new_v4(), Window::All).games, 0);
+    }
+
+    #[test]
+    fn leaderboard_by_score() {
+        let ids: Vec<_> = (0..4).map(|_| Uuid::new_v4()).collect();
+        let mut stats = Stats::default();
+        stats.record(ids[0], record(1, 3, true, 4));
+        // same score in more games ranks lower
+        stats.record(ids[1], record(1, 3, false, 2));
+        stats.record(ids[1], record(1, 3, false, 2));
+        stats.record(ids[2], record(1, 3, false, -2));
+        stats.record(ids[3], record(40, 3, false, 10));
+
+        let board: Vec<_> = stats
+            .leaderboard(Window::Month, 10)
+            .into_iter()
+            .map(|(id, summary)| (id, summary.score))
+            .collect();
+        assert_eq!(board, [(ids[0], 4), (ids[1], 4), (ids[2], -2)]);
+        assert_eq!(stats.leaderboard(Window::All, 1)[0].0, ids[3]);
+    }
+
+    #[test]
+    fn rankings_by_mode() {
+        let ids: Vec<_> = (0..3).map(|_| Uuid::new_v4()).collect();
+        let mut stats = Stats::default();
+        for (id, rating) in ids.iter().zip([1400.0, 1600.0, 1700.0]) {
+            stats.set_rating(*id, 3, rating);
+            stats.record(*id, record(0, 3, false, 1));
+        }
+        // rated for four players without having played it
+        stats.set_rating(ids[2], 4, 2000.0);
+
+        assert_eq!(stats.rankings(3, 2), [(ids[2], 1700.0), (ids[1], 1600.0)]);
+        assert!(stats.rankings(4, 10).is_empty());
+        stats.record(ids[2], record(0, 4, false, 1));
+        assert_eq!(stats.rankings(4, 10), [(ids[2], 2000.0)]);
+    }
+}
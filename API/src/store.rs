@@ -0,0 +1,74 @@
+use std::{
+    collections::HashMap,
+    env, io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::{fs, time};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::account::Account;
+use crate::app::AppState;
+use crate::lobby::Snapshot;
+use crate::stats::Stats;
+
+const INTERVAL: Duration = Duration::from_secs(30);
+
+// sessions, accounts and lobbies saved to disk
+#[derive(Default, Deserialize, Serialize)]
+pub struct Saved {
+    pub users: HashMap<Uuid, String>,  // guest sessions
+    pub sessions: HashMap<Uuid, Uuid>, // account sessions
+    pub accounts: HashMap<Uuid, Account>,
+    pub stats: Stats,
+    pub lobbies: HashMap<String, Snapshot>,
+}
+
+// location of the save file, set by DATA_DIR
+pub fn path() -> PathBuf {
+    let dir = env::var("DATA_DIR").unwrap_or_else(|_| "data".to_string());
+    Path::new(&dir).join("state.json")
+}
+
+pub async fn save(state: &AppState, path: &Path) -> io::Result<()> {
+    let saved = state.snapshot().await;
+    let json = serde_json::to_vec(&saved)?;
+
+    // write to a temporary file first so a crash never leaves a partial save
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).await?;
+    }
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, json).await?;
+    fs::rename(&tmp, path).await
+}
+
+pub async fn load(state: &AppState, path: &Path) -> io::Result<()> {
+    let json = match fs::read(path).await {
+        Ok(json) => json,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+    let saved: Saved = serde_json::from_slice(&json)?;
+    info!(
+        users = saved.users.len(),
+        lobbies = saved.lobbies.len(),
+        "restoring saved state"
+    );
+    state.restore(saved).await;
+    Ok(())
+}
+
+pub async fn autosave(state: AppState, path: PathBuf) {
+    let mut interval = time::interval(INTERVAL);
+    interval.tick().await;
+    loop {
+        interval.tick().await;
+        if let Err(e) = save(&state, &path).await {
+            warn!(error = e.to_string(), "autosave failed");
+        }
+    }
+}
@@ -0,0 +1,410 @@
+use std::str::FromStr;
+
+use rand::{seq::SliceRandom, Rng, RngCore};
+use serde::{Deserialize, Serialize};
+
+use crate::card::{self, Card, Hand};
+use crate::rules::{BidMode, Rules};
+
+// everything a player is allowed to see of the game
+#[derive(Clone, Debug)]
+pub struct View {
+    pub idx: usize,
+    pub players: usize,
+    pub rules: Rules,
+    pub cards: Vec<Card>,
+    pub cards_left: Vec<usize>,
+    pub bid: usize,
+    pub landlord: Option<usize>, // None while bidding
+    pub doubling: bool,
+    pub revealed: Vec<Option<Vec<Card>>>, // open hands
+    pub bonus: Vec<Card>,                 // empty while bidding
+    pub last_idx: usize,
+    pub last_play: Hand,
+    pub passes: usize,
+    pub played: Vec<Card>,
+}
+impl View {
+    // turn the bid a player would like to make into a legal one
+    pub fn legal_bid(&self, val: usize) -> usize {
+        match self.rules.bid_mode {
+            BidMode::Points if val > self.bid => val.min(self.rules.max_bid),
+            BidMode::Points => 0,
+            BidMode::Rob => (val > self.bid) as usize,
+        }
+    }
+
+    pub fn can_pass(&self) -> bool {
+        !self.last_play.is_pass()
+    }
+
+    // peasants play together against the landlord
+    pub fn is_partner(&self, other: usize) -> bool {
+        match self.landlord {
+            Some(landlord) => self.idx != landlord && other != landlord,
+            None => false,
+        }
+    }
+
+    pub fn legal_plays(&self) -> Vec<Hand> {
+        Hand::legal_plays(&self.rules, &self.cards, &self.last_play)
+    }
+}
+
+pub enum Action {
+    Bid(usize),
+    Double(bool),
+    Play(Hand),
+}
+
+pub trait Strategy: Sync {
+    // bid of 0 denotes pass
+    fn bid(&self, view: &View, rng: &mut dyn RngCore) -> usize;
+    fn double(&self, view: &View, rng: &mut dyn RngCore) -> bool;
+    fn play(&self, view: &View, rng: &mut dyn RngCore) -> Hand;
+
+    fn act(&self, view: &View, rng: &mut dyn RngCore) -> Action {
+        if view.landlord.is_none() {
+            Action::Bid(self.bid(view, rng))
+        } else if view.doubling {
+            Action::Double(self.double(view, rng))
+        } else {
+            Action::Play(self.play(view, rng))
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize)]
+pub enum Level {
+    Easy,
+    #[default]
+    Medium,
+    Hard,
+}
+impl FromStr for Level {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "easy" | "random" => Ok(Level::Easy),
+            "medium" | "greedy" => Ok(Level::Medium),
+            "hard" | "search" => Ok(Level::Hard),
+            _ => Err(format!("unknown strategy {}", s)),
+        }
+    }
+}
+impl Level {
+    pub fn strategy(self) -> &'static dyn Strategy {
+        match self {
+            Level::Easy => &Random,
+            Level::Medium => &Greedy,
+            Level::Hard => &Search { rollouts: 400 },
+        }
+    }
+}
+
+// picks uniformly among legal moves
+pub struct Random;
+impl Strategy for Random {
+    fn bid(&self, view: &View, rng: &mut dyn RngCore) -> usize {
+        view.legal_bid(rng.random_range(0..=3))
+    }
+
+    fn double(&self, _: &View, rng: &mut dyn RngCore) -> bool {
+        rng.random()
+    }
+
+    fn play(&self, view: &View, rng: &mut dyn RngCore) -> Hand {
+        let mut plays = view.legal_plays();
+        if view.can_pass() {
+            plays.push(Hand::PASS);
+        }
+        let i = rng.random_range(..plays.len());
+        plays.swap_remove(i)
+    }
+}
+
+// sheds low cards first and holds bombs until an opponent is about to win
+pub struct Greedy;
+impl Strategy for Greedy {
+    fn bid(&self, view: &View, _: &mut dyn RngCore) -> usize {
+        view.legal_bid(strength(&view.cards) / 2)
+    }
+
+    fn double(&self, view: &View, _: &mut dyn RngCore) -> bool {
+        strength(&view.cards) >= 4
+    }
+
+    fn play(&self, view: &View, _: &mut dyn RngCore) -> Hand {
+        let mut plays = view.legal_plays();
+
+        // go out if possible
+        if let Some(i) = plays
+            .iter()
+            .position(|h| h.cards().len() == view.cards.len())
+        {
+            return plays.swap_remove(i);
+        }
+
+        if !view.can_pass() {
+            // longest hand using the lowest card
+            let lowest = view.cards[0].rank();
+            let lead = plays
+                .iter()
+                .enumerate()
+                .filter(|(_, h)| !h.is_bomb() && h.cards()[0].rank() == lowest)
+                .max_by_key(|(i, h)| (h.cards().len(), usize::MAX - i))
+                .map(|(i, _)| i)
+                .unwrap_or(0);
+            return plays.swap_remove(lead);
+        }
+
+        // let a partner's play stand
+        if view.is_partner(view.last_idx) {
+            return Hand::PASS;
+        }
+
+        // plays of the same type are generated from lowest to highest
+        if let Some(i) = plays.iter().position(|h| !h.is_bomb()) {
+            return plays.swap_remove(i);
+        }
+        if !plays.is_empty() && view.cards_left[view.last_idx] <= 4 {
+            return plays.swap_remove(0);
+        }
+        Hand::PASS
+    }
+}
+
+// count high cards and bombs
+fn strength(cards: &[Card]) -> usize {
+    let mut cnts = [0; 15];
+    for card in cards {
+        cnts[card.rank()] += 1;
+    }
+    let bombs = cnts[..13].iter().filter(|&&c| c >= 4).count();
+    cnts[12] + cnts[13] + cnts[14] + 2 * bombs
+}
+
+// tries each move against random deals of the unseen cards
+// the rest of each game is played out greedily
+pub struct Search {
+    pub rollouts: usize, // spread across all candidate moves
+}
+impl Strategy for Search {
+    fn bid(&self, view: &View, rng: &mut dyn RngCore) -> usize {
+        Greedy.bid(view, rng)
+    }
+
+    fn double(&self, view: &View, rng: &mut dyn RngCore) -> bool {
+        Greedy.double(view, rng)
+    }
+
+    fn play(&self, view: &View, rng: &mut dyn RngCore) -> Hand {
+        // only the lowest kickers are considered for each shape
+        let mut options: Vec<Hand> = Vec::new();
+        for hand in view.legal_plays() {
+            if !options.iter().any(|h| h.same_shape(&hand)) {
+                options.push(hand);
+            }
+        }
+        if view.can_pass() {
+            options.push(Hand::PASS);
+        }
+        if options.len() == 1 {
+            return options.pop().unwrap();
+        }
+
+        let per_option = (self.rollouts / options.len()).max(4);
+        let mut wins = vec![0; options.len()];
+        for _ in 0..per_option {
+            let hands = deal_unseen(view, rng);
+            for (i, option) in options.iter().enumerate() {
+                let winner = Sim::new(view, hands.clone()).finish(option.clone(), rng);
+                if winner == view.idx || view.is_partner(winner) {
+                    wins[i] += 1;
+                }
+            }
+        }
+
+        // ties go to the earlier, cheaper move
+        let best = (0..options.len())
+            .max_by_key(|&i| (wins[i], usize::MAX - i))
+            .unwrap();
+        options.swap_remove(best)
+    }
+}
+
+// guess the other hands from the cards not yet seen
+fn deal_unseen(view: &View, rng: &mut dyn RngCore) -> Vec<Vec<Card>> {
+    let mut unseen = card::deck(view.players);
+    for card in view.cards.iter().chain(&view.played) {
+        remove(&mut unseen, card);
+    }
+
+    let mut hands = vec![Vec::new(); view.players];
+    hands[view.idx] = view.cards.clone();
+    // open hands are known exactly
+    for (i, cards) in view.revealed.iter().enumerate() {
+        if let Some(cards) = cards.as_ref().filter(|_| i != view.idx) {
+            for card in cards {
+                remove(&mut unseen, card);
+            }
+            hands[i] = cards.clone();
+        }
+    }
+    // the landlord still holds any bonus cards that have not been played
+    if let Some(landlord) = view.landlord.filter(|&l| l != view.idx) {
+        let mut held = view.bonus.clone();
+        for card in &view.played {
+            remove(&mut held, card);
+        }
+        for card in held {
+            if hands[landlord].len() < view.cards_left[landlord] && remove(&mut unseen, &card) {
+                hands[landlord].push(card);
+            }
+        }
+    }
+
+    unseen.shuffle(rng);
+    for (i, hand) in hands.iter_mut().enumerate() {
+        if i == view.idx {
+            continue;
+        }
+        let needed = view.cards_left[i]
+            .saturating_sub(hand.len())
+            .min(unseen.len());
+        hand.extend(unseen.drain(..needed));
+        hand.sort();
+    }
+    hands
+}
+
+fn remove(cards: &mut Vec<Card>, card: &Card) -> bool {
+    if let Some(pos) = cards.iter().position(|c| c == card) {
+        cards.swap_remove(pos);
+        true
+    } else {
+        false
+    }
+}
+
+// plays out a game with every player using the greedy strategy
+struct Sim {
+    view: View,
+    hands: Vec<Vec<Card>>,
+}
+impl Sim {
+    const MAX_TURNS: usize = 500;
+
+    fn new(view: &View, hands: Vec<Vec<Card>>) -> Self {
+        Self {
+            view: view.clone(),
+            hands,
+        }
+    }
+
+    // returns the winner after the current player plays hand
+    fn finish(mut self, mut hand: Hand, rng: &mut dyn RngCore) -> usize {
+        for _ in 0..Self::MAX_TURNS {
+            let idx = self.view.idx;
+            if hand.is_pass() {
+                self.view.passes += 1;
+                if self.view.passes == self.view.players - 1 {
+                    self.view.passes = 0;
+                    self.view.last_play = Hand::PASS;
+                }
+            } else {
+                for card in hand.cards() {
+                    if let Some(pos) = self.hands[idx].iter().position(|c| c == card) {
+                        self.hands[idx].remove(pos);
+                    }
+                }
+                if self.hands[idx].is_empty() {
+                    return idx;
+                }
+                self.view.cards_left[idx] = self.hands[idx].len();
+                self.view.passes = 0;
+                self.view.last_idx = idx;
+                self.view.last_play = hand;
+            }
+
+            // move to the next player
+            let next = (idx + 1) % self.view.players;
+            self.view.idx = next;
+            self.view.cards = self.hands[next].clone();
+            hand = Greedy.play(&self.view, rng);
+        }
+        // give up and call it for whoever has the fewest cards
+        (0..self.view.players)
+            .min_by_key(|&i| self.hands[i].len())
+            .unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Game;
+
+    // play a full game between strategies under random house rules
+    // checking every move is accepted
+    fn play_out(players: usize, strategies: &[&dyn Strategy]) {
+        let mut rng = rand::rng();
+        let rules = Rules {
+            triple_single: rng.random(),
+            quad_single: rng.random(),
+            high_kickers: rng.random(),
+            quad_kicker_bomb: rng.random(),
+            min_chain: [rng.random_range(3..=5), rng.random_range(2..=3), 2],
+            max_bid: rng.random_range(1..=5),
+            bid_mode: if rng.random() {
+                BidMode::Points
+            } else {
+                BidMode::Rob
+            },
+            doubling: rng.random(),
+            ..Rules::standard(players)
+        };
+        let new_game = || {
+            Game::new(players, Game::random_seed())
+                .unwrap()
+                .with_rules(rules.clone())
+        };
+        let mut game = new_game();
+        while game.winner().is_none() {
+            let idx = game.turn();
+            match strategies[idx].act(&game.view(idx), &mut rng) {
+                Action::Bid(val) => {
+                    if game.bid(idx, val).unwrap() {
+                        game = new_game();
+                    }
+                }
+                Action::Double(val) => game.double(idx, val).unwrap(),
+                Action::Play(hand) => game.play(idx, hand).unwrap(),
+            }
+        }
+    }
+
+    #[test]
+    fn random_moves_are_legal() {
+        for _ in 0..20 {
+            play_out(3, &[&Random, &Random, &Random]);
+            play_out(4, &[&Random, &Random, &Random, &Random]);
+        }
+    }
+
+    #[test]
+    fn greedy_moves_are_legal() {
+        for _ in 0..20 {
+            play_out(3, &[&Greedy, &Random, &Greedy]);
+            play_out(4, &[&Greedy, &Greedy, &Random, &Greedy]);
+        }
+    }
+
+    #[test]
+    fn search_moves_are_legal() {
+        let search = Search { rollouts: 20 };
+        play_out(3, &[&search, &Greedy, &Random]);
+        play_out(4, &[&search, &Greedy, &Random, &search]);
+    }
+}
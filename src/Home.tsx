@@ -1,24 +1,73 @@
 import "./home.css";
 
-import { useEffect } from "react";
+import { useEffect, useRef, useState } from "react";
 import { Form, useNavigate } from "react-router";
 
 import fetchJson from "./fetchJson";
 import FormError from "./FormError";
 import useUser from "./login/UserContext";
+import { Listing, MatchmakingMsg, Settings } from "./types";
 
 export default function Home() {
   const user = useUser();
   const navigate = useNavigate();
 
+  const [lobbies, setLobbies] = useState<Listing[]>([]);
+  const [hidden, setHidden] = useState(false);
+  const [queued, setQueued] = useState<string | null>(null);
+  const queueRef = useRef<WebSocket | null>(null);
+
   useEffect(() => {
     document.title = "Home";
+    fetchJson("/api/lobbies?filter=joinable").then(setLobbies, () => {});
+    return () => queueRef.current?.close();
   }, []);
 
-  async function handleCreate() {
+  // closing the socket leaves the queue
+  async function handleQuickPlay(seats: 3 | 4) {
+    queueRef.current?.close();
+    await fetchJson("/api/matchmaking/join", {
+      method: "POST",
+      credentials: "include",
+      headers: { "Content-Type": "application/json" },
+      body: JSON.stringify({ seats }),
+    });
+
+    const protocol = window.location.protocol == "https:" ? "wss" : "ws";
+    const ws = new WebSocket(
+      `${protocol}://${window.location.host}/api/matchmaking/ws`,
+    );
+    queueRef.current = ws;
+    setQueued(`Looking for a ${seats} player game...`);
+    ws.onmessage = (e: MessageEvent) => {
+      const data: MatchmakingMsg = JSON.parse(e.data);
+      if ("Queued" in data) {
+        setQueued(
+          `Looking for a ${seats} player game, ${data.Queued.waiting} waiting...`,
+        );
+      } else if ("Matched" in data) {
+        navigate(`/lobby/${data.Matched.lobbyCode}`);
+      } else {
+        setQueued(data.Error);
+      }
+    };
+  }
+
+  function handleCancel() {
+    queueRef.current?.close();
+    queueRef.current = null;
+    setQueued(null);
+  }
+
+  async function handleCreate(seats: 3 | 4) {
     const { lobbyCode } = await fetchJson("/api/create", {
       method: "POST",
       credentials: "include",
+      headers: { "Content-Type": "application/json" },
+      body: JSON.stringify({
+        seats,
+        private: hidden,
+      } satisfies Partial<Settings>),
     });
     navigate(`/lobby/${lobbyCode}`);
   }
@@ -62,10 +111,69 @@ export default function Home() {
           </div>
           <FormError />
         </Form>
+        {lobbies.length > 0 && (
+          <ul className="lobby-list">
+            {lobbies.map(({ code, players, seats, bid_mode }) => (
+              <li key={code}>
+                <Form method="POST">
+                  <span className="medium">{code}</span>{" "}
+                  {players.join(", ")} ({players.length}/{seats}
+                  {bid_mode == "Rob" ? ", rob the landlord" : ""})
+                  <input type="hidden" name="lobbyCode" value={code} />
+                  <button className="btn-secondary" type="submit">
+                    Join
+                  </button>
+                </Form>
+              </li>
+            ))}
+          </ul>
+        )}
         <hr className="separator" />
-        <button className="create-btn btn-primary" onClick={handleCreate}>
-          Create A New Lobby
-        </button>
+        {queued ? (
+          <div className="form-row">
+            <span>{queued}</span>
+            <button className="btn-secondary" onClick={handleCancel}>
+              Cancel
+            </button>
+          </div>
+        ) : (
+          <div className="form-row">
+            <button
+              className="create-btn btn-primary"
+              onClick={() => handleQuickPlay(3)}
+            >
+              Quick Play 3 Players
+            </button>
+            <button
+              className="create-btn btn-primary"
+              onClick={() => handleQuickPlay(4)}
+            >
+              Quick Play 4 Players
+            </button>
+          </div>
+        )}
+        <div className="form-row">
+          <button
+            className="create-btn btn-primary"
+            onClick={() => handleCreate(3)}
+          >
+            New 3 Player Lobby
+          </button>
+          <button
+            className="create-btn btn-primary"
+            onClick={() => handleCreate(4)}
+          >
+            New 4 Player Lobby
+          </button>
+        </div>
+        <label>
+          <input
+            type="checkbox"
+            checked={hidden}
+            onChange={(e) => setHidden(e.currentTarget.checked)}
+          />{" "}
+          Private, only joinable by code
+        </label>
       </div>
     </div>
   );
@@ -19,7 +19,11 @@ export default function Game() {
 
   const [lobbyState, setLobbyState] = useState(initialLobbyState);
   const [socket, setSocket] = useState<WebSocket | null>(null);
+  // positions in the hand of the last suggested play
+  const [hint, setHint] = useState<number[] | null>(null);
   const [trigger, setTrigger] = useState(false);
+  // last update seen, sent on reconnect to receive what was missed
+  const seqRef = useRef(initialLobbyState.seq);
 
   const [error, setError] = useState("");
   const timeoutRef = useRef<NodeJS.Timeout | null>(null);
@@ -39,21 +43,27 @@ export default function Game() {
   // this happens on the transition from spectator to user
   useEffect(() => {
     setLobbyState(initialLobbyState);
+    seqRef.current = initialLobbyState.seq;
   }, [initialLobbyState]);
 
   // connect websocket
   useEffect(() => {
     const protocol = window.location.protocol == "https:" ? "wss" : "ws";
     const ws = new WebSocket(
-      `${protocol}://${window.location.host}/api/lobby/${lobbyCode}/ws`,
+      `${protocol}://${window.location.host}/api/lobby/${lobbyCode}/ws?seq=${seqRef.current}`,
     );
     setSocket(ws);
 
     // handle game state update messages
     ws.onmessage = (e: MessageEvent) => {
       const data: ServerMsg = JSON.parse(e.data);
+      if ("seq" in data) {
+        seqRef.current = data.seq;
+      }
       if ("State" in data) {
         setLobbyState(data.State);
+      } else if ("Hint" in data) {
+        setHint(data.Hint);
       } else if ("Error" in data) {
         flashError(data.Error);
       }
@@ -78,16 +88,34 @@ export default function Game() {
     };
   }, [socket]);
 
+  // tick once a second while the turn is timed
+  const deadline = lobbyState.game?.deadline;
+  const [now, setNow] = useState(Date.now());
+  useEffect(() => {
+    if (!deadline) return;
+    const interval = setInterval(() => setNow(Date.now()), 1000);
+    return () => {
+      clearInterval(interval);
+    };
+  }, [deadline]);
+
   // generate notification
   let msg;
   if (error) {
     msg = `Error: ${error}`;
-  } else if (lobbyState.status == "Bidding" || lobbyState.status == "Playing") {
+  } else if (
+    lobbyState.status == "Bidding" ||
+    lobbyState.status == "Doubling" ||
+    lobbyState.status == "Playing"
+  ) {
     msg = `${
       lobbyState.game!.turn == lobbyState.idx
         ? "Your"
         : `${lobbyState.players[lobbyState.game!.turn].name}'s`
     } turn`;
+    if (deadline) {
+      msg += ` (${Math.max(0, Math.ceil((deadline - now) / 1000))}s)`;
+    }
   }
 
   return (
@@ -98,7 +126,7 @@ export default function Game() {
         {msg && <div className="game-notification hover-box medium">{msg}</div>}
         <Table />
         <Chat initial={initialMessages} />
-        <Hand hand={lobbyState.hand || []} />
+        <Hand hand={lobbyState.hand || []} hint={hint} />
       </div>
     </GameContext.Provider>
   );
@@ -5,6 +5,30 @@ import { GameContextType } from "@/types";
 export const GameContext = createContext<GameContextType>({
   status: "Lobby",
   players: [],
+  seq: 0,
+  rules: {
+    triple_single: true,
+    triple_pair: true,
+    quad_single: true,
+    quad_pair: true,
+    single_rocket: true,
+    high_kickers: true,
+    quad_kicker_bomb: false,
+    double_bomb_size: 4,
+    min_chain: [5, 3, 2],
+    max_bid: 3,
+    bid_mode: "Points",
+    doubling: false,
+    reveal: false,
+  },
+  host: null,
+  waiting: [],
+  settings: {
+    seats: 3,
+    rules: null,
+    turn_limit: null,
+    private: false,
+  },
   socket: null,
 } satisfies GameContextType);
 
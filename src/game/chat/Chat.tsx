@@ -13,7 +13,7 @@ type ChatProps = {
 };
 export default function Chat({ initial }: ChatProps) {
   const { lobbyCode } = useParams();
-  const { players, idx, socket } = useGame();
+  const { idx, queued, socket } = useGame();
 
   const [messages, setMessages] = useState(initial);
   const [loading, setLoading] = useState(true);
@@ -83,6 +83,11 @@ export default function Chat({ initial }: ChatProps) {
       const data: ServerMsg = JSON.parse(e.data);
       if ("Chat" in data) {
         setMessages((prev) => {
+          // already loaded before reconnecting
+          if (prev.some((msg) => msg.seq == data.Chat.seq)) {
+            return prev;
+          }
+
           // scroll to bottom if at bottom
           const chat = chatRef.current;
           if (
@@ -120,16 +125,16 @@ export default function Chat({ initial }: ChatProps) {
         <div ref={sentinelRef} className="sentinel">
           {loading && "Loading..."}
         </div>
-        {messages.map(({ text, idx, time }) => (
+        {messages.map(({ text, idx, time, name }) => (
           <div key={time}>
             <b className={`player-${idx}`}>
-              {idx == 9 ? "Game" : players[idx].name}:{" "}
+              {name ?? "Game"}:{" "}
             </b>
             {text}
           </div>
         ))}
       </div>
-      {idx != null && (
+      {(idx != null || queued != null) && (
         <input
           className="chat-input"
           type="text"
@@ -5,15 +5,16 @@ import { Form, useParams } from "react-router";
 
 import FormError from "@/FormError";
 import useUser from "@/login/UserContext";
-import { ClientMsg } from "@/types";
+import { ClientMsg, Level } from "@/types";
 
 import Card from "../card/Card";
 import useGame from "../GameContext";
 
 type HandProps = {
   hand: number[];
+  hint: number[] | null;
 };
-export default function Hand({ hand }: HandProps) {
+export default function Hand({ hand, hint }: HandProps) {
   const [selected, setSelected] = useState<boolean[]>(
     new Array(hand.length).fill(false),
   );
@@ -31,6 +32,14 @@ export default function Hand({ hand }: HandProps) {
   }
   useEffect(clearHand, [hand.length]);
 
+  // select the suggested cards
+  useEffect(() => {
+    if (!hint) return;
+    const next = new Array(hand.length).fill(false);
+    hint.forEach((i) => (next[i] = true));
+    setSelected(next);
+  }, [hint]);
+
   return (
     <>
       <div className="game-hand cards">
@@ -61,23 +70,48 @@ function Actions({ hand, selected, clearHand }: ActionsProps) {
   const { lobbyCode } = useParams();
 
   const user = useUser();
-  const { status, players, idx, game, socket } = useGame();
+  const { status, players, idx, game, rules, settings, socket } = useGame();
+  const { hosting, queued } = useGame();
+  const [level, setLevel] = useState<Level>("Medium");
+
+  const idle = status == "Lobby" || status == "Finished";
+  function leaveBtn() {
+    return (
+      <button
+        className="btn-secondary"
+        onClick={() =>
+          socket?.send(JSON.stringify({ Leave: null } satisfies ClientMsg))
+        }
+      >
+        Leave
+      </button>
+    );
+  }
 
+  if (queued != undefined) {
+    return (
+      <div className="game-actions">
+        <span className="medium">
+          {queued == 0 ? "You are next in line" : `${queued} ahead of you`}
+        </span>
+        {hosting && idle && startBtn("Start Next Game")}
+        {leaveBtn()}
+      </div>
+    );
+  }
   if (idx == undefined) {
-    if ((status == "Lobby" || status == "Finished") && players.length < 4) {
-      return (
-        <div className="game-actions">
-          <Form action="/" method="POST">
-            <button className="btn-primary" type="submit">
-              Join game as {user}
-            </button>
-            <input type="hidden" name="lobbyCode" value={lobbyCode} />
-            <FormError />
-          </Form>
-        </div>
-      );
-    }
-    return null;
+    const seated = idle && players.length < settings.seats;
+    return (
+      <div className="game-actions">
+        <Form action="/" method="POST">
+          <button className="btn-primary" type="submit">
+            {seated ? `Join game as ${user}` : `Wait for a seat as ${user}`}
+          </button>
+          <input type="hidden" name="lobbyCode" value={lobbyCode} />
+          <FormError />
+        </Form>
+      </div>
+    );
   }
 
   const notTurn = idx != game?.turn;
@@ -97,8 +131,29 @@ function Actions({ hand, selected, clearHand }: ActionsProps) {
 
   let actions;
   if (status == "Lobby") {
-    if (players.length >= 3) {
+    if (players.length == settings.seats && hosting) {
       actions = startBtn("Start Game");
+    } else if (hosting) {
+      actions = (
+        <>
+          <select
+            value={level}
+            onChange={(e) => setLevel(e.currentTarget.value as Level)}
+          >
+            <option>Easy</option>
+            <option>Medium</option>
+            <option>Hard</option>
+          </select>
+          <button
+            className="btn-secondary"
+            onClick={() =>
+              socket?.send(JSON.stringify({ AddBot: level } satisfies ClientMsg))
+            }
+          >
+            Add Bot
+          </button>
+        </>
+      );
     }
   } else if (status == "Bidding") {
     function bid(val: number) {
@@ -106,27 +161,50 @@ function Actions({ hand, selected, clearHand }: ActionsProps) {
         socket?.send(JSON.stringify({ Bid: val } satisfies ClientMsg));
     }
 
+    actions =
+      rules.bid_mode == "Rob" ? (
+        <>
+          <button className="btn-primary" onClick={bid(1)} disabled={notTurn}>
+            {game!.bid == 0 ? "Call" : "Rob"}
+          </button>
+          <button className="btn-secondary" onClick={bid(0)} disabled={notTurn}>
+            Pass
+          </button>
+        </>
+      ) : (
+        <>
+          {[...Array(rules.max_bid)].map((_, i) => (
+            <button
+              key={i}
+              className="btn-primary"
+              onClick={bid(i + 1)}
+              disabled={notTurn || game!.bid >= i + 1}
+            >
+              {i + 1}
+            </button>
+          ))}
+          <button className="btn-secondary" onClick={bid(0)} disabled={notTurn}>
+            Pass
+          </button>
+        </>
+      );
+  } else if (status == "Doubling") {
+    function double(val: boolean) {
+      return () =>
+        socket?.send(JSON.stringify({ Double: val } satisfies ClientMsg));
+    }
+
     actions = (
       <>
-        <button
-          className="btn-primary"
-          onClick={bid(1)}
-          disabled={notTurn || game!.bid >= 1}
-        >
-          1
+        <button className="btn-primary" onClick={double(true)} disabled={notTurn}>
+          {idx == game!.landlord ? "Redouble" : "Double"}
         </button>
         <button
-          className="btn-primary"
-          onClick={bid(2)}
-          disabled={notTurn || game!.bid >= 2}
+          className="btn-secondary"
+          onClick={double(false)}
+          disabled={notTurn}
         >
-          2
-        </button>
-        <button className="btn-primary" onClick={bid(3)} disabled={notTurn}>
-          3
-        </button>
-        <button className="btn-secondary" onClick={bid(0)} disabled={notTurn}>
-          Pass
+          Skip
         </button>
       </>
     );
@@ -136,6 +214,15 @@ function Actions({ hand, selected, clearHand }: ActionsProps) {
     }
     actions = (
       <>
+        <button
+          className="btn-secondary"
+          onClick={() =>
+            socket?.send(JSON.stringify({ Hint: null } satisfies ClientMsg))
+          }
+          disabled={notTurn}
+        >
+          Hint
+        </button>
         <button
           className="btn-primary"
           onClick={() => {
@@ -154,7 +241,7 @@ function Actions({ hand, selected, clearHand }: ActionsProps) {
         </button>
       </>
     );
-  } else {
+  } else if (hosting) {
     actions = startBtn("Play Again");
   }
 
@@ -165,7 +252,19 @@ function Actions({ hand, selected, clearHand }: ActionsProps) {
           Clear
         </button>
       )}
+      {rules.reveal &&
+        (status == "Bidding" || status == "Doubling") &&
+        !game?.revealed?.[idx] && (
+          <button
+            onClick={() =>
+              socket?.send(JSON.stringify({ Reveal: null } satisfies ClientMsg))
+            }
+          >
+            Reveal hand
+          </button>
+        )}
       {actions}
+      {idle && leaveBtn()}
     </div>
   );
 }
@@ -3,24 +3,128 @@ import "./info.css";
 import { Fragment } from "react";
 import { useParams } from "react-router";
 
+import { ClientMsg, Rules } from "@/types";
+
 import useGame from "../GameContext";
 
 export default function Info() {
   const { lobbyCode } = useParams();
-  const { players, game } = useGame();
+  const { status, players, waiting, settings, game, idx: self } = useGame();
+  const { rules, hosting, queued, socket } = useGame();
+  const canKick = hosting && (status == "Lobby" || status == "Finished");
+  const turnLimits = [15, 30, 60, 120];
+  const houseRules = [
+    ["triple_single", "Triple with a single"],
+    ["triple_pair", "Triple with a pair"],
+    ["quad_single", "Quad with two singles"],
+    ["quad_pair", "Quad with two pairs"],
+    ["single_rocket", "One of each joker is a rocket"],
+    ["high_kickers", "2s and jokers as kickers"],
+    ["quad_kicker_bomb", "Quads with kickers beat like bombs"],
+    ["doubling", "Doubling after bidding"],
+    ["reveal", "Reveal hand to double"],
+  ] as const;
+
+  // null goes back to the standard rules
+  function setRules(rules: Rules | null) {
+    socket?.send(JSON.stringify({ Settings: { rules } } satisfies ClientMsg));
+  }
+
+  function kick(idx: number, ban: boolean, waiting = false) {
+    socket?.send(
+      JSON.stringify({ Kick: { idx, waiting, ban } } satisfies ClientMsg),
+    );
+  }
 
   return (
     <div className="game-info overlay">
       <h1>
         <span className="lobby-code">{lobbyCode}</span>
       </h1>
+      <p className="medium">
+        {settings.seats == 3 ? "3 players" : "4 players, double deck"}
+        <br />
+        {canKick ? (
+          <select
+            value={settings.turn_limit ?? ""}
+            onChange={(e) => {
+              const turn_limit = Number(e.currentTarget.value) || null;
+              socket?.send(
+                JSON.stringify({
+                  Settings: { turn_limit },
+                } satisfies ClientMsg),
+              );
+            }}
+          >
+            <option value="">No turn limit</option>
+            {turnLimits.map((secs) => (
+              <option key={secs} value={secs}>
+                {secs}s per turn
+              </option>
+            ))}
+          </select>
+        ) : (
+          settings.turn_limit && `${settings.turn_limit}s per turn`
+        )}
+      </p>
+      <div className="info-rules">
+        <h3>RULES</h3>
+        <select
+          className="medium"
+          value={rules.bid_mode}
+          disabled={!canKick}
+          onChange={(e) =>
+            setRules({
+              ...rules,
+              bid_mode: e.currentTarget.value as Rules["bid_mode"],
+            })
+          }
+        >
+          <option value="Points">Bid points</option>
+          <option value="Rob">Rob the landlord</option>
+        </select>
+        {houseRules.map(([rule, label]) => (
+          <label key={rule} className="medium">
+            <input
+              type="checkbox"
+              checked={rules[rule]}
+              disabled={!canKick}
+              onChange={(e) =>
+                setRules({ ...rules, [rule]: e.currentTarget.checked })
+              }
+            />
+            {label}
+          </label>
+        ))}
+        {canKick && settings.rules && (
+          <button onClick={() => setRules(null)}>Standard rules</button>
+        )}
+      </div>
       <div className="info-scores">
         <h3>SCOREBOARD</h3>
         <div className="info-players">
           {players.map(({ name, score }, idx) => (
             <Fragment key={idx}>
               <span className={`player-icon player-${idx}`} />
-              <span className="medium">{name}</span>
+              <span className="medium">
+                {name}
+                {canKick && idx != self && (
+                  <>
+                    <button
+                      className="info-kick"
+                      onClick={() => kick(idx, false)}
+                    >
+                      kick
+                    </button>
+                    <button
+                      className="info-kick"
+                      onClick={() => kick(idx, true)}
+                    >
+                      ban
+                    </button>
+                  </>
+                )}
+              </span>
               <span
                 className={`player-score${
                   score == 0
@@ -33,6 +137,37 @@ export default function Info() {
             </Fragment>
           ))}
         </div>
+        {waiting.length > 0 && (
+          <>
+            <h3>WAITING</h3>
+            <div className="info-waiting medium">
+              {waiting.map(({ name, score }, i) => (
+                <Fragment key={i}>
+                  <span>
+                    {name}
+                    {hosting && i != queued && (
+                      <>
+                        <button
+                          className="info-kick"
+                          onClick={() => kick(i, false, true)}
+                        >
+                          kick
+                        </button>
+                        <button
+                          className="info-kick"
+                          onClick={() => kick(i, true, true)}
+                        >
+                          ban
+                        </button>
+                      </>
+                    )}
+                  </span>
+                  <span>{score}</span>
+                </Fragment>
+              ))}
+            </div>
+          </>
+        )}
       </div>
       {game && "landlord" in game && (
         <div>
@@ -7,20 +7,23 @@ import Card from "../card/Card";
 import useGame from "../GameContext";
 
 export default function Table() {
-  const { status, players, game } = useGame();
+  const { status, players, settings, game } = useGame();
 
   let text;
   let field;
   let emph;
   if (status == "Lobby") {
-    if (players.length < 3) {
-      text = "Waiting for players...";
+    if (players.length < settings.seats) {
+      text = `Waiting for players... (${players.length}/${settings.seats})`;
     } else {
       text = "Ready to start.";
     }
   } else if (status == "Bidding") {
     text = "Bidding phase.";
     emph = `Current bid: ${game!.bid || "none"}`;
+  } else if (status == "Doubling") {
+    text = "Doubling phase.";
+    emph = `${players[game!.landlord!].name} is the landlord`;
   } else {
     const last_play = game!.last_play!;
     text = last_play.cards.length
@@ -98,6 +101,13 @@ function Player({ pos }: PlayerProps) {
           {game.cards_left[i]} card{game.cards_left[i] == 1 ? "" : "s"}
         </div>
       )}
+      {i != idx && game?.revealed?.[i] && (
+        <div className="cards">
+          {game.revealed[i].map((card, j) => (
+            <Card key={j} card={card} small />
+          ))}
+        </div>
+      )}
     </div>
   );
 }
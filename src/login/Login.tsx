@@ -25,9 +25,24 @@ export default function Login() {
             required
             autoFocus
           />
-          <button className="btn-primary" type="submit">
+          <input
+            type="password"
+            name="password"
+            placeholder="Password, blank to play as a guest"
+          />
+        </div>
+        <div className="form-row">
+          <button className="btn-primary" type="submit" name="intent">
             Login
           </button>
+          <button
+            className="btn-secondary"
+            type="submit"
+            name="intent"
+            value="register"
+          >
+            Register
+          </button>
           <input type="hidden" name="to" value={to} />
         </div>
       </Form>
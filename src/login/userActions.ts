@@ -9,21 +9,27 @@ import { userContext } from "./authMiddleware";
 export async function loginAction({ request }: ActionFunctionArgs) {
   const data = await request.formData();
   const username = (data.get("username") as string).trim();
+  const password = data.get("password") as string;
+  const register = data.get("intent") == "register";
   const to = data.get("to") as string;
 
   // validate username
   if (!username) {
     return { error: "Username cannot be empty" };
   }
+  if (register && !password) {
+    return { error: "Choose a password to register" };
+  }
 
-  const resp = await fetch("/api/login", {
+  const resp = await fetch(register ? "/api/register" : "/api/login", {
     method: "POST",
     headers: { "Content-Type": "application/x-www-form-urlencoded" },
-    body: new URLSearchParams({ username }),
+    body: new URLSearchParams({ username, password }),
     credentials: "include",
   });
   if (!resp.ok) {
-    return { error: "Login failed" };
+    const error = await resp.json().catch(() => ({}));
+    return { error: error.msg || "Login failed" };
   }
 
   return redirect(to);
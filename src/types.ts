@@ -8,19 +8,70 @@ export type ClientMsg =
   | { Chat: string }
   | { Start: null }
   | { Bid: number }
-  | { Play: number[] };
+  | { Double: boolean }
+  | { Reveal: null }
+  | { Settings: Partial<Settings> }
+  | { Leave: null }
+  | { Kick: { idx: number; waiting?: boolean; ban: boolean } }
+  | { Play: number[] }
+  | { Hint: null }
+  | { AddBot: Level };
 
 export type Msg = {
   text: string;
   idx: number;
   time: number;
+  seq: number;
+  name?: string;
 };
 
 export type ServerMsg =
-  | { Chat: Msg }
-  | { State: LobbyState }
+  | ({ seq: number } & ({ Chat: Msg } | { State: LobbyState }))
+  | { Hint: number[] }
   | { Error: string };
 
+export type MatchmakingMsg =
+  | { Queued: { seats: 3 | 4; waiting: number } }
+  | { Matched: { lobbyCode: string } }
+  | { Error: string };
+
+export type Rules = {
+  triple_single: boolean;
+  triple_pair: boolean;
+  quad_single: boolean;
+  quad_pair: boolean;
+  single_rocket: boolean;
+  high_kickers: boolean;
+  quad_kicker_bomb: boolean;
+  double_bomb_size: number;
+  min_chain: [number, number, number];
+  max_bid: number;
+  bid_mode: "Points" | "Rob";
+  doubling: boolean;
+  reveal: boolean;
+};
+
+export type Level = "Easy" | "Medium" | "Hard";
+
+export type Settings = {
+  seats: 3 | 4;
+  rules: Rules | null;
+  turn_limit: number | null;
+  private: boolean;
+};
+
+export type Listing = {
+  code: string;
+  players: string[];
+  seats: 3 | 4;
+  waiting: number;
+  status: LobbyState["status"];
+  bid_mode: Rules["bid_mode"];
+  custom_rules: boolean;
+  joinable: boolean;
+  in_progress: boolean;
+};
+
 export type GameState = {
   turn: number;
   bid: number;
@@ -35,6 +86,9 @@ export type GameState = {
   landlord?: number;
   bonus?: number[];
   winner?: number;
+  deadline?: number;
+  doubles?: number[];
+  revealed?: (number[] | null)[];
 };
 
 export type Player = {
@@ -43,8 +97,15 @@ export type Player = {
 };
 
 export type LobbyState = {
-  status: "Lobby" | "Bidding" | "Playing" | "Finished";
+  status: "Lobby" | "Bidding" | "Doubling" | "Playing" | "Finished";
   players: Player[];
+  seq: number;
+  rules: Rules;
+  host: number | null;
+  settings: Settings;
+  waiting: Player[];
+  hosting?: boolean;
+  queued?: number;
   idx?: number;
   hand?: number[];
   game?: GameState;